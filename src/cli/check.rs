@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use super::report::{Format, Report, Row, Status};
+use crate::error::Error;
+use crate::repository::Upgrade;
+use crate::widget::service_switcher::ServiceSwitcher;
+use crate::Args;
+
+/// exit code when every image is up to date
+const UP_TO_DATE: i32 = 0;
+/// exit code when at least one image has an upgrade of at least the threshold
+const OUTDATED: i32 = 1;
+/// exit code when at least one image could not be checked, it takes precedence over OUTDATED
+const FAILED: i32 = 2;
+
+/// prints the current and the newest compatible tag of every service
+/// and returns the exit code
+pub fn run(opt: &Args, threshold: Upgrade, format: Format) -> Result<i32, Error> {
+    let switcher = super::open_compose_files(&opt.file)?;
+    Ok(check(&switcher, threshold, format))
+}
+
+/// checks the images of every file below a directory like `run`
+pub fn scan(dir: &Path, threshold: Upgrade, format: Format) -> Result<i32, Error> {
+    let (switcher, errors) = super::scan_files(dir)?;
+    for e in &errors {
+        eprintln!("skipping: {}", e);
+    }
+    let code = check(&switcher, threshold, format);
+    match errors.is_empty() {
+        true => Ok(code),
+        false => Ok(FAILED),
+    }
+}
+
+#[tokio::main]
+async fn check(switcher: &ServiceSwitcher, threshold: Upgrade, format: Format) -> i32 {
+    let (rows, code) = check_images(switcher, threshold).await;
+    Report::new(rows).print(format);
+    code
+}
+
+/// looks up every image and returns a row for each of them and the exit code
+async fn check_images(switcher: &ServiceSwitcher, threshold: Upgrade) -> (Vec<Row>, i32) {
+    let images = super::collect(switcher, &switcher.image_locations()).await;
+
+    let mut outdated = false;
+    let mut failed = false;
+    let mut rows = vec![];
+    for image in &images {
+        let newest = image.newest(Upgrade::Major);
+        let status = match (&image.upgrades, newest) {
+            (Err(e), _) => {
+                eprintln!("{}: {}", image.reference.name(), e);
+                Status::Error
+            }
+            (Ok(_), Some((kind, _))) if *kind >= threshold => Status::Outdated,
+            (Ok(_), _) => Status::UpToDate,
+        };
+        failed |= status == Status::Error;
+        outdated |= status == Status::Outdated;
+        rows.push(Row::new(image, newest, status));
+    }
+
+    let code = match (failed, outdated) {
+        (true, _) => FAILED,
+        (false, true) => OUTDATED,
+        (false, false) => UP_TO_DATE,
+    };
+    (rows, code)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{FAILED, OUTDATED, UP_TO_DATE};
+    use crate::cli::report::Status;
+    use crate::repository::mock_registry::{MockRegistry, Response};
+    use crate::repository::Upgrade;
+    use crate::widget::service_switcher::ServiceSwitcher;
+
+    /// a registry where org/app has a patch, a minor and a major upgrade of 1.0.0,
+    /// org/patch only a patch upgrade and the tags of org/missing can't be listed
+    fn registry() -> MockRegistry {
+        MockRegistry::start(|request| match request.path.as_str() {
+            "/v2/org/app/tags/list" => {
+                Response::ok(r#"{"tags":["1.0.0","1.0.1","1.1.0","2.0.0"]}"#)
+            }
+            "/v2/org/patch/tags/list" => Response::ok(r#"{"tags":["1.0.0","1.0.1"]}"#),
+            _ => Response::status(404),
+        })
+    }
+
+    async fn check(dir: &Path, images: &[String], threshold: Upgrade) -> (Vec<Status>, i32) {
+        let mut content = String::from("services:\n");
+        for (i, image) in images.iter().enumerate() {
+            content.push_str(&format!("  service{}:\n    image: {}\n", i, image));
+        }
+        let path = dir.join("compose.yaml");
+        std::fs::write(&path, content).unwrap();
+        let switcher = ServiceSwitcher::new(&[path]).unwrap();
+
+        let (rows, code) = super::check_images(&switcher, threshold).await;
+        (rows.iter().map(|r| r.status).collect(), code)
+    }
+
+    #[tokio::test]
+    async fn test_up_to_date() {
+        let registry = registry();
+        let temp = tempfile::tempdir().unwrap();
+        let image = format!("{}/org/app:2.0.0", registry.host());
+
+        let result = check(temp.path(), &[image], Upgrade::Patch).await;
+        assert_eq!(result, (vec![Status::UpToDate], UP_TO_DATE));
+    }
+
+    #[tokio::test]
+    async fn test_threshold() {
+        let registry = registry();
+        let temp = tempfile::tempdir().unwrap();
+        let image = format!("{}/org/patch:1.0.0", registry.host());
+
+        //a patch upgrade is below the minor threshold
+        let below = check(temp.path(), std::slice::from_ref(&image), Upgrade::Minor).await;
+        assert_eq!(below, (vec![Status::UpToDate], UP_TO_DATE));
+        let at = check(temp.path(), &[image], Upgrade::Patch).await;
+        assert_eq!(at, (vec![Status::Outdated], OUTDATED));
+    }
+
+    #[tokio::test]
+    async fn test_failed_takes_precedence() {
+        let registry = registry();
+        let temp = tempfile::tempdir().unwrap();
+        let images = [
+            format!("{}/org/app:1.0.0", registry.host()),
+            format!("{}/org/missing:1.0.0", registry.host()),
+        ];
+
+        let result = check(temp.path(), &images, Upgrade::Patch).await;
+        assert_eq!(result, (vec![Status::Outdated, Status::Error], FAILED));
+    }
+}
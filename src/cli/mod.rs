@@ -0,0 +1,121 @@
+pub mod check;
+pub mod report;
+pub mod tags;
+pub mod update;
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::repo::Reference;
+use crate::repository::{self, Tag, Upgrade};
+use crate::widget::service_switcher::{self, Location, ServiceSwitcher};
+
+/// an image line of a compose file together with the newer tags of its repository
+pub struct ImageStatus {
+    pub service: Option<String>,
+    /// the file and line that define the image
+    pub location: Location,
+    pub file: PathBuf,
+    /// the image as it is written, with resolved variables
+    pub image: String,
+    pub reference: Reference,
+    /// the tag that is in use if the registry knows it
+    pub current: Option<Tag>,
+    /// the newest tag per kind of upgrade, ordered patch, minor, major
+    pub upgrades: Result<Vec<(Upgrade, Tag)>, Error>,
+}
+
+impl ImageStatus {
+    /// returns the newest tag that is not a bigger step than the given kind of upgrade
+    pub fn newest(&self, up_to: Upgrade) -> Option<&(Upgrade, Tag)> {
+        match &self.upgrades {
+            Err(_) => None,
+            Ok(upgrades) => upgrades.iter().rev().find(|(kind, _)| *kind <= up_to),
+        }
+    }
+}
+
+/// opens the compose files the same way the tui does
+pub fn open_compose_files(files: &[PathBuf]) -> Result<ServiceSwitcher, Error> {
+    ServiceSwitcher::new(files).map_err(|e| match e {
+        service_switcher::Error::NoFile => Error::NoComposeFile,
+        e => Error::ComposeFile(e.to_string()),
+    })
+}
+
+/// opens every file below a directory that contains images
+/// the errors of the files that could not be opened are returned with the switcher
+pub fn scan_files(dir: &Path) -> Result<(ServiceSwitcher, Vec<service_switcher::Error>), Error> {
+    ServiceSwitcher::scan(dir).map_err(|e| match e {
+        service_switcher::Error::NoFile => Error::NoImageFile(dir.to_path_buf()),
+        e => Error::ComposeFile(e.to_string()),
+    })
+}
+
+/// looks up the images at the given locations in their registries
+pub async fn collect(switcher: &ServiceSwitcher, locations: &[Location]) -> Vec<ImageStatus> {
+    let mut result = vec![];
+    for location in locations.iter().copied() {
+        let file = switcher.file_path(location.file).to_path_buf();
+        let position = format!("{}:{}", file.display(), location.line + 1);
+        let image = match switcher.get_image(location) {
+            Ok(image) => image,
+            Err(e) => {
+                eprintln!("{}: skipping: {}", position, e);
+                continue;
+            }
+        };
+        let reference = match Reference::parse(&image) {
+            Ok(reference) => reference,
+            Err(e) => {
+                eprintln!("{}: skipping {}: {}", position, image, e);
+                continue;
+            }
+        };
+        let (current, upgrades) = match lookup(&reference).await {
+            Ok((current, upgrades)) => (current, Ok(upgrades)),
+            Err(e) => (None, Err(e)),
+        };
+
+        result.push(ImageStatus {
+            service: switcher.service_name(location),
+            location,
+            file,
+            image,
+            reference,
+            current,
+            upgrades,
+        });
+    }
+    result
+}
+
+/// returns the current tag and the suggested upgrades
+/// tags without platform details get them fetched
+async fn lookup(reference: &Reference) -> Result<(Option<Tag>, Vec<(Upgrade, Tag)>), Error> {
+    let Some(name) = &reference.tag else {
+        return Ok((None, vec![]));
+    };
+    let repo = repository::Repo::new(&reference.to_string()).await?;
+    let tags = repo.fetch_all_tags().await?;
+
+    let mut current = tags.iter().find(|t| t.get_name() == name).cloned();
+    if let Some(tag) = &mut current {
+        with_details(&repo, tag).await;
+    }
+    let mut upgrades = repository::suggest_upgrades(name, &tags);
+    for (_, tag) in upgrades.iter_mut() {
+        with_details(&repo, tag).await;
+    }
+    Ok((current, upgrades))
+}
+
+/// replaces a tag with one that carries platform details, a failed request keeps the tag
+async fn with_details(repo: &repository::Repo, tag: &mut Tag) {
+    if !tag.get_details().is_empty() {
+        return;
+    }
+    if let Ok(fetched) = repo.fetch_tag(tag.get_name()).await {
+        *tag = fetched;
+    }
+}
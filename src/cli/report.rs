@@ -0,0 +1,336 @@
+use chrono::DateTime;
+use serde::Serialize;
+
+use super::ImageStatus;
+use crate::common::display_duration_ext::DisplayDurationExt;
+use crate::repo;
+use crate::repository::{Tag, Upgrade};
+
+/// the version of the json output, it is increased on every incompatible change
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// the ways a report can be printed
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+    Markdown,
+}
+
+/// the outcome for one image line
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    UpToDate,
+    Outdated,
+    Updated,
+    Error,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::UpToDate => write!(f, "up to date"),
+            Status::Outdated => write!(f, "outdated"),
+            Status::Updated => write!(f, "updated"),
+            Status::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub images: Vec<Row>,
+}
+
+#[derive(Serialize)]
+pub struct Row {
+    pub service: Option<String>,
+    pub file: String,
+    /// the line number, starting at 1
+    pub line: usize,
+    pub current: TagInfo,
+    pub newest: Option<Candidate>,
+    pub status: Status,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TagInfo {
+    /// the image as it is written in the file
+    pub reference: String,
+    /// the reference with the docker hub domain removed and `library/` added to official images
+    pub normalized: String,
+    pub tag: Option<String>,
+    /// the time of the last push in rfc3339
+    pub last_updated: Option<String>,
+    pub digest: Option<String>,
+    /// platforms in the form of os/arch/variant
+    pub platforms: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Candidate {
+    pub upgrade: Upgrade,
+    #[serde(flatten)]
+    pub tag: TagInfo,
+}
+
+/// the platforms of a tag in the form of os/arch/variant
+pub fn platforms(tag: &Tag) -> Vec<String> {
+    let mut platforms: Vec<String> = tag
+        .get_details()
+        .iter()
+        .filter_map(|d| {
+            let mut platform = format!("{}/{}", d.os.as_deref()?, d.arch.as_deref()?);
+            if let Some(variant) = d.variant.as_deref().filter(|v| !v.is_empty()) {
+                platform = format!("{}/{}", platform, variant);
+            }
+            Some(platform)
+        })
+        .collect();
+    platforms.dedup();
+    platforms
+}
+
+impl TagInfo {
+    fn new(
+        reference: String,
+        normalized: String,
+        tag_name: Option<String>,
+        tag: Option<&Tag>,
+    ) -> Self {
+        Self {
+            reference,
+            normalized,
+            tag: tag_name,
+            last_updated: tag.and_then(|t| t.get_last_updated().map(String::from)),
+            digest: tag.and_then(|t| t.get_digest().map(String::from)),
+            platforms: tag.map(platforms).unwrap_or_default(),
+        }
+    }
+
+    /// the time since the last push in a human readable form
+    fn age(&self) -> String {
+        let Some(last_updated) = &self.last_updated else {
+            return String::from("-");
+        };
+        match DateTime::parse_from_rfc3339(last_updated) {
+            Err(_) => String::from("-"),
+            Ok(time) => (chrono::Utc::now() - time.with_timezone(&chrono::Utc)).display(),
+        }
+    }
+}
+
+impl Row {
+    /// creates a row of an image with the chosen candidate
+    pub fn new(image: &ImageStatus, newest: Option<&(Upgrade, Tag)>, status: Status) -> Self {
+        let name = image.reference.name();
+        //the repository as it is written, to write the newest tag the same way
+        let (written, _) =
+            repo::split_tag_from_repo(&image.image).unwrap_or((image.image.as_str(), ""));
+        Self {
+            service: image.service.clone(),
+            file: image.file.display().to_string(),
+            line: image.location.line + 1,
+            current: TagInfo::new(
+                image.image.clone(),
+                image.reference.to_string(),
+                image.reference.tag.clone(),
+                image.current.as_ref(),
+            ),
+            newest: newest.map(|(upgrade, tag)| Candidate {
+                upgrade: *upgrade,
+                tag: TagInfo::new(
+                    format!("{}:{}", written, tag.get_name()),
+                    format!("{}:{}", name, tag.get_name()),
+                    Some(tag.get_name().to_string()),
+                    Some(tag),
+                ),
+            }),
+            status,
+            error: image.upgrades.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+
+    /// sets the error that made the image fail
+    pub fn with_error(mut self, error: &dyn std::fmt::Display) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    fn cells(&self) -> [String; 10] {
+        let newest = self.newest.as_ref();
+        [
+            self.service.clone().unwrap_or_default(),
+            self.file.clone(),
+            self.line.to_string(),
+            self.current.reference.clone(),
+            self.current.age(),
+            newest
+                .and_then(|n| n.tag.tag.clone())
+                .unwrap_or(String::from("-")),
+            newest.map(|n| n.tag.age()).unwrap_or(String::from("-")),
+            newest
+                .map(|n| n.upgrade.to_string())
+                .unwrap_or(String::from("-")),
+            newest
+                .map(|n| n.tag.platforms.join(" "))
+                .unwrap_or_default(),
+            self.status.to_string(),
+        ]
+    }
+}
+
+const HEADER: [&str; 10] = [
+    "SERVICE",
+    "FILE",
+    "LINE",
+    "IMAGE",
+    "AGE",
+    "NEWEST",
+    "AGE",
+    "UPGRADE",
+    "PLATFORMS",
+    "STATUS",
+];
+
+impl Report {
+    pub fn new(images: Vec<Row>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            images,
+        }
+    }
+
+    /// prints the report to stdout
+    pub fn print(&self, format: Format) {
+        match format {
+            Format::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(self).expect("report is serializable")
+            ),
+            Format::Table => {
+                let mut rows = vec![HEADER.map(String::from)];
+                rows.extend(self.images.iter().map(Row::cells));
+                print_table(&rows);
+            }
+            Format::Markdown => print!("{}", self.markdown()),
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = String::new();
+        let row = |cells: &[String]| format!("| {} |\n", cells.join(" | "));
+        let header = HEADER.map(|h| format!("{}{}", &h[..1], h[1..].to_lowercase()));
+        out.push_str(&row(&header));
+        out.push_str(&row(&header.map(|_| String::from("---"))));
+        for image in &self.images {
+            let cells = image.cells().map(|c| c.replace('|', "\\|"));
+            out.push_str(&row(&cells));
+        }
+        out
+    }
+}
+
+/// prints rows with columns that are aligned to the widest cell
+pub fn print_table<const N: usize>(rows: &[[String; N]]) {
+    let mut widths = [0; N];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Candidate, Report, Row, Status, TagInfo};
+    use crate::cli::ImageStatus;
+    use crate::repo::Reference;
+    use crate::repository::Upgrade;
+    use crate::widget::service_switcher::Location;
+
+    fn tag_info(reference: &str, tag: &str) -> TagInfo {
+        TagInfo {
+            reference: reference.into(),
+            normalized: format!("library/{}", reference),
+            tag: Some(tag.into()),
+            last_updated: Some(String::from("2024-01-01T00:00:00Z")),
+            digest: None,
+            platforms: vec![String::from("linux/arm64/v8")],
+        }
+    }
+
+    fn report() -> Report {
+        Report::new(vec![Row {
+            service: Some(String::from("web")),
+            file: String::from("docker-compose.yml"),
+            line: 3,
+            current: tag_info("nginx:1.25", "1.25"),
+            newest: Some(Candidate {
+                upgrade: Upgrade::Minor,
+                tag: tag_info("nginx:1.27", "1.27"),
+            }),
+            status: Status::Outdated,
+            error: None,
+        }])
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let json = serde_json::to_value(report()).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        let row = &json["images"][0];
+        assert_eq!(row["service"], "web");
+        assert_eq!(row["file"], "docker-compose.yml");
+        assert_eq!(row["line"], 3);
+        assert_eq!(row["status"], "outdated");
+        assert_eq!(row["current"]["reference"], "nginx:1.25");
+        assert_eq!(row["current"]["normalized"], "library/nginx:1.25");
+        assert_eq!(row["current"]["last_updated"], "2024-01-01T00:00:00Z");
+        assert_eq!(row["newest"]["upgrade"], "minor");
+        assert_eq!(row["newest"]["tag"], "1.27");
+        assert_eq!(row["newest"]["platforms"][0], "linux/arm64/v8");
+        assert!(row["error"].is_null());
+    }
+
+    #[test]
+    fn test_written_reference() {
+        let image = "docker.io/library/nginx:1.25";
+        let status = ImageStatus {
+            service: Some(String::from("web")),
+            location: Location { file: 0, line: 1 },
+            file: PathBuf::from("web.container"),
+            image: String::from(image),
+            reference: Reference::parse(image).unwrap(),
+            current: None,
+            upgrades: Ok(vec![]),
+        };
+        let row = Row::new(&status, None, Status::UpToDate);
+        assert_eq!(row.current.reference, "docker.io/library/nginx:1.25");
+        assert_eq!(row.current.normalized, "library/nginx:1.25");
+        assert_eq!(row.line, 2);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = report().markdown();
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("| Service | File | Line | Image |"));
+        assert!(lines[2].starts_with("| web | docker-compose.yml | 3 | nginx:1.25 |"));
+        assert!(lines[2].ends_with("| minor | linux/arm64/v8 | outdated |"));
+    }
+}
@@ -0,0 +1,235 @@
+use regex::Regex;
+use serde::Serialize;
+
+use super::report::{self, Format};
+use crate::error::Error;
+use crate::repository::{self, Tag};
+
+/// the version of the json listing, it is increased on every incompatible change
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct TagListing {
+    schema_version: u32,
+    repository: String,
+    tags: Vec<TagRow>,
+}
+
+#[derive(Serialize)]
+struct TagRow {
+    name: String,
+    /// the time of the last push in rfc3339
+    last_updated: Option<String>,
+    digest: Option<String>,
+    /// platforms in the form of os/arch/variant
+    platforms: Vec<String>,
+}
+
+impl TagRow {
+    fn new(tag: &Tag) -> Self {
+        Self {
+            name: tag.get_name().to_string(),
+            last_updated: tag.get_last_updated().map(String::from),
+            digest: tag.get_digest().map(String::from),
+            platforms: report::platforms(tag),
+        }
+    }
+
+    fn cells(&self) -> [String; 3] {
+        [
+            self.name.clone(),
+            self.last_updated.clone().unwrap_or(String::from("-")),
+            self.digest.clone().unwrap_or(String::from("-")),
+        ]
+    }
+}
+
+const HEADER: [&str; 3] = ["TAG", "LAST UPDATED", "DIGEST"];
+
+/// prints the tags of a repository, pages are fetched until the limit is reached
+#[tokio::main]
+pub async fn run(
+    repo: &str,
+    limit: Option<usize>,
+    filter: &Option<Regex>,
+    arch: &Option<String>,
+    format: Format,
+) -> Result<(), Error> {
+    let name = repository::check_repo(repo)?;
+    let rows = list(repo, limit, filter, arch).await?;
+
+    match format {
+        Format::Json => {
+            let listing = TagListing {
+                schema_version: SCHEMA_VERSION,
+                repository: name,
+                tags: rows,
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&listing).expect("tags are serializable")
+            );
+        }
+        Format::Table => {
+            let mut cells = vec![HEADER.map(String::from)];
+            cells.extend(rows.iter().map(TagRow::cells));
+            report::print_table(&cells);
+        }
+        Format::Markdown => {
+            println!("| Tag | Last updated | Digest |");
+            println!("| --- | --- | --- |");
+            for row in &rows {
+                println!("| {} |", row.cells().join(" | "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// collects the matching tags of all pages until the limit is reached
+async fn list(
+    repo: &str,
+    limit: Option<usize>,
+    filter: &Option<Regex>,
+    arch: &Option<String>,
+) -> Result<Vec<TagRow>, Error> {
+    let mut page = repository::Repo::new(repo).await?;
+    let mut rows = vec![];
+    'pages: loop {
+        for tag in page.get_tags() {
+            if limit.is_some_and(|limit| rows.len() >= limit) {
+                break 'pages;
+            }
+            if filter.as_ref().is_some_and(|f| !f.is_match(tag.get_name())) {
+                continue;
+            }
+            let Some(arch) = arch else {
+                rows.push(TagRow::new(tag));
+                continue;
+            };
+
+            //registries that list tags without details need a request per tag
+            //a tag that can't be fetched is skipped instead of failing the listing
+            let tag = match tag.get_details().is_empty() {
+                true => match page.fetch_tag(tag.get_name()).await {
+                    Ok(tag) => tag,
+                    Err(e) => {
+                        eprintln!("{}: skipping: {}", tag.get_name(), e);
+                        continue;
+                    }
+                },
+                false => tag.clone(),
+            };
+            if supports_arch(&tag, arch) {
+                rows.push(TagRow::new(&tag));
+            }
+        }
+        if limit.is_some_and(|limit| rows.len() >= limit) {
+            break;
+        }
+        match page.next_page().await? {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+    Ok(rows)
+}
+
+/// checks if the tag has an image for the architecture, e.g. arm64 or arm64/v8
+fn supports_arch(tag: &Tag, arch: &str) -> bool {
+    tag.get_details().iter().any(|d| {
+        let Some(tag_arch) = d.arch.as_deref() else {
+            return false;
+        };
+        match d.variant.as_deref().filter(|v| !v.is_empty()) {
+            Some(variant) => arch == tag_arch || arch == format!("{}/{}", tag_arch, variant),
+            None => arch == tag_arch,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::repository::mock_registry::{MockRegistry, Response};
+
+    /// a registry that lists the tags of org/app on two pages, the second page of org/broken fails
+    /// the tags of org/app are arm64 images, except 2.0 which is amd64, the manifest of 1.1 is missing
+    fn registry() -> MockRegistry {
+        MockRegistry::start(|request| match request.path.as_str() {
+            "/v2/org/app/tags/list" | "/v2/org/broken/tags/list" => {
+                let next = request.path.replace("list", "list?last=1.1&n=3");
+                Response::ok(r#"{"tags":["1.0","1.1","latest"]}"#)
+                    .with_header("Link", &format!(r#"<{}>; rel="next""#, next))
+            }
+            "/v2/org/app/tags/list?last=1.1&n=3" => Response::ok(r#"{"tags":["2.0","2.1"]}"#),
+            "/v2/org/app/manifests/1.1" => Response::status(404),
+            "/v2/org/app/manifests/2.0" => {
+                Response::ok(r#"{ "config": { "digest": "sha256:amd", "size": 1 }, "layers": [] }"#)
+            }
+            path if path.starts_with("/v2/org/app/manifests/") => {
+                Response::ok(r#"{ "config": { "digest": "sha256:arm", "size": 1 }, "layers": [] }"#)
+            }
+            "/v2/org/app/blobs/sha256:amd" => {
+                Response::ok(r#"{ "architecture": "amd64", "os": "linux", "rootfs": {} }"#)
+            }
+            "/v2/org/app/blobs/sha256:arm" => {
+                Response::ok(r#"{ "architecture": "arm64", "os": "linux", "rootfs": {} }"#)
+            }
+            _ => Response::status(500),
+        })
+    }
+
+    async fn names(
+        repo: &str,
+        limit: Option<usize>,
+        filter: Option<&str>,
+        arch: Option<&str>,
+    ) -> Result<Vec<String>, crate::error::Error> {
+        let filter = filter.map(|f| Regex::new(f).unwrap());
+        let arch = arch.map(String::from);
+        let rows = super::list(repo, limit, &filter, &arch).await?;
+        Ok(rows.into_iter().map(|r| r.name).collect())
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let registry = registry();
+        let repo = format!("{}/org/app", registry.host());
+
+        //all pages are followed
+        let all = names(&repo, None, None, None).await.unwrap();
+        assert_eq!(all, vec!["1.0", "1.1", "latest", "2.0", "2.1"]);
+        let limited = names(&repo, Some(4), None, None).await.unwrap();
+        assert_eq!(limited, vec!["1.0", "1.1", "latest", "2.0"]);
+        let filtered = names(&repo, Some(2), Some(r"^\d+\.1$"), None)
+            .await
+            .unwrap();
+        assert_eq!(filtered, vec!["1.1", "2.1"]);
+    }
+
+    #[tokio::test]
+    async fn test_arch() {
+        let registry = registry();
+        let repo = format!("{}/org/app", registry.host());
+
+        //the tag with the missing manifest is skipped
+        let arm = names(&repo, None, None, Some("arm64")).await.unwrap();
+        assert_eq!(arm, vec!["1.0", "latest", "2.1"]);
+        let amd = names(&repo, None, None, Some("amd64")).await.unwrap();
+        assert_eq!(amd, vec!["2.0"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_page() {
+        let registry = registry();
+        let repo = format!("{}/org/broken", registry.host());
+        assert!(names(&repo, None, None, None).await.is_err());
+        //the limit is reached before the failing page
+        assert_eq!(
+            names(&repo, Some(3), None, None).await.unwrap(),
+            vec!["1.0", "1.1", "latest"]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use super::report::{Format, Report, Row, Status};
+use crate::error::Error;
+use crate::repo;
+use crate::repository::{self, Upgrade};
+use crate::widget::service_switcher::ServiceSwitcher;
+use crate::Args;
+
+/// exit code when every image could be looked up
+const SUCCESS: i32 = 0;
+/// exit code when at least one image could not be looked up
+const FAILED: i32 = 2;
+
+/// rewrites the image lines to the newest tag allowed by the policy and saves the file
+/// with dry_run a unified diff of the changes is printed instead of the report and nothing is saved
+#[tokio::main]
+pub async fn run(
+    opt: &Args,
+    policy: Upgrade,
+    service: &Option<String>,
+    format: Format,
+    dry_run: bool,
+) -> Result<i32, Error> {
+    let mut switcher = super::open_compose_files(&opt.file)?;
+    let (rows, updated) = update(&mut switcher, policy, service).await?;
+    let failed = rows.iter().any(|r| r.status == Status::Error);
+
+    if dry_run {
+        print!("{}", switcher.diff()?);
+    } else {
+        if updated > 0 {
+            switcher
+                .save(opt.backup)
+                .map_err(|e| Error::ComposeFile(e.to_string()))?;
+        }
+        Report::new(rows).print(format);
+    }
+
+    match failed {
+        true => Ok(FAILED),
+        false => Ok(SUCCESS),
+    }
+}
+
+/// changes the image lines of the service or of all services without saving them
+/// the variant of a tag is kept and images that were pinned by digest get pinned again
+/// returns a row for every image and the number of changed images
+async fn update(
+    switcher: &mut ServiceSwitcher,
+    policy: Upgrade,
+    service: &Option<String>,
+) -> Result<(Vec<Row>, usize), Error> {
+    //only the images of the service are looked up
+    let mut locations = switcher.image_locations();
+    if let Some(service) = service {
+        locations.retain(|l| switcher.service_name(*l).as_ref() == Some(service));
+        if locations.is_empty() {
+            return Err(Error::UnknownService(service.clone()));
+        }
+    }
+    let images = super::collect(switcher, &locations).await;
+
+    let mut updated = 0;
+    let mut rows = vec![];
+    for image in &images {
+        if let Err(e) = &image.upgrades {
+            eprintln!("{}: {}", image.reference.name(), e);
+            rows.push(Row::new(image, None, Status::Error));
+            continue;
+        }
+        let Some(newest) = image.newest(policy) else {
+            rows.push(Row::new(image, None, Status::UpToDate));
+            continue;
+        };
+        let tag = &newest.1;
+
+        let name = match repo::split_tag_from_repo(&image.image) {
+            Ok((name, _)) => name,
+            Err(e) => {
+                eprintln!("{}: {}", image.image, e);
+                rows.push(Row::new(image, Some(newest), Status::Error).with_error(&e));
+                continue;
+            }
+        };
+        let mut new = format!("{}:{}", name, tag.get_name());
+
+        //keep images pinned
+        if image.reference.digest.is_some() {
+            let digest = match tag.get_digest() {
+                Some(digest) => Ok(digest.to_string()),
+                None => pinned_digest(&image.reference.to_string(), tag.get_name()).await,
+            };
+            match digest {
+                Ok(digest) => new = format!("{}@{}", new, digest),
+                Err(e) => {
+                    eprintln!("{}: {}", image.reference.name(), e);
+                    rows.push(Row::new(image, Some(newest), Status::Error).with_error(&e));
+                    continue;
+                }
+            }
+        }
+
+        switcher.select(image.location);
+        if let Err(e) = switcher.change_current_line(new) {
+            eprintln!("{}: {}", image.reference.name(), e);
+            rows.push(Row::new(image, Some(newest), Status::Error).with_error(&e));
+            continue;
+        }
+        updated += 1;
+        rows.push(Row::new(image, Some(newest), Status::Updated));
+    }
+    Ok((rows, updated))
+}
+
+/// fetches the digest of a tag when the tag list did not contain it
+async fn pinned_digest(image: &str, tag: &str) -> Result<String, Error> {
+    let tag = repository::Repo::new(image).await?.fetch_tag(tag).await?;
+    tag.get_digest().map(String::from).ok_or(Error::NoTagFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use crate::cli::report::Status;
+    use crate::error::Error;
+    use crate::repository::mock_registry::{MockRegistry, Response};
+    use crate::repository::Upgrade;
+    use crate::widget::service_switcher::{Location, ServiceSwitcher};
+
+    /// a registry with a patch, a minor and a major upgrade of 1.0.0 for every repository
+    /// the manifests of `org/unpinned` are missing, the requested paths are logged
+    fn registry(log: Arc<Mutex<Vec<String>>>) -> MockRegistry {
+        MockRegistry::start(move |request| {
+            let path = request.path.clone();
+            log.lock().unwrap().push(path.clone());
+            if path.ends_with("/tags/list") {
+                return Response::ok(r#"{"tags":["1.0.0","1.0.1","1.1.0","2.0.0"]}"#);
+            }
+            if path.ends_with("/blobs/sha256:c1") {
+                return Response::ok(r#"{ "architecture": "amd64", "os": "linux", "rootfs": {} }"#);
+            }
+            match path.rsplit_once("/manifests/") {
+                Some((repository, _)) if repository.ends_with("org/unpinned") => {
+                    Response::status(404)
+                }
+                Some((_, tag)) => Response::ok(
+                    r#"{ "config": { "digest": "sha256:c1", "size": 1 }, "layers": [] }"#,
+                )
+                .with_header(
+                    "Docker-Content-Digest",
+                    &format!("sha256:{}", tag.replace('.', "")),
+                ),
+                None => Response::status(404),
+            }
+        })
+    }
+
+    fn compose(dir: &Path, images: &[(&str, &str)]) -> ServiceSwitcher {
+        let mut content = String::from("services:\n");
+        for (service, image) in images {
+            content.push_str(&format!("  {}:\n    image: {}\n", service, image));
+        }
+        let path = dir.join("compose.yaml");
+        std::fs::write(&path, content).unwrap();
+        ServiceSwitcher::new(&[path]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_policy() {
+        let registry = registry(Arc::default());
+        let temp = tempfile::tempdir().unwrap();
+        let image = format!("{}/org/app:1.0.0", registry.host());
+
+        for (policy, tag) in [
+            (Upgrade::Patch, "1.0.1"),
+            (Upgrade::Minor, "1.1.0"),
+            (Upgrade::Major, "2.0.0"),
+        ] {
+            let mut switcher = compose(temp.path(), &[("web", &image)]);
+            let (rows, updated) = super::update(&mut switcher, policy, &None).await.unwrap();
+            let line = switcher.get_image(Location { file: 0, line: 2 }).unwrap();
+
+            assert_eq!(updated, 1);
+            assert_eq!(rows[0].status, Status::Updated);
+            assert_eq!(line, image.replace("1.0.0", tag));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let registry = registry(log.clone());
+        let temp = tempfile::tempdir().unwrap();
+        let web = format!("{}/org/web:1.0.0", registry.host());
+        let db = format!("{}/org/db:1.0.0", registry.host());
+        let mut switcher = compose(temp.path(), &[("web", &web), ("db", &db)]);
+
+        let service = Some(String::from("db"));
+        let (rows, updated) = super::update(&mut switcher, Upgrade::Minor, &service)
+            .await
+            .unwrap();
+
+        assert_eq!(updated, 1);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].service.as_deref(), Some("db"));
+        assert_eq!(
+            switcher.get_image(Location { file: 0, line: 2 }).unwrap(),
+            web
+        );
+        //the other service was not looked up
+        assert!(log.lock().unwrap().iter().all(|p| !p.contains("org/web")));
+
+        let unknown = Some(String::from("cache"));
+        assert!(matches!(
+            super::update(&mut switcher, Upgrade::Minor, &unknown).await,
+            Err(Error::UnknownService(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_digest() {
+        let registry = registry(Arc::default());
+        let temp = tempfile::tempdir().unwrap();
+        let pinned = format!("{}/org/app:1.0.0@sha256:100", registry.host());
+        let unpinned = format!("{}/org/unpinned:1.0.0@sha256:100", registry.host());
+        let mut switcher = compose(temp.path(), &[("web", &pinned), ("db", &unpinned)]);
+
+        let (rows, updated) = super::update(&mut switcher, Upgrade::Minor, &None)
+            .await
+            .unwrap();
+
+        assert_eq!(updated, 1);
+        assert_eq!(
+            switcher.get_image(Location { file: 0, line: 2 }).unwrap(),
+            format!("{}/org/app:1.1.0@sha256:110", registry.host())
+        );
+        //the digest of the new tag could not be fetched
+        assert_eq!(rows[1].status, Status::Error);
+        assert!(rows[1].error.is_some());
+        assert_eq!(
+            switcher.get_image(Location { file: 0, line: 4 }).unwrap(),
+            unpinned
+        );
+    }
+}
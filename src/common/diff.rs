@@ -0,0 +1,261 @@
+/// lines of unchanged context around a change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// returns the edit script from old to new, based on the longest common subsequence
+fn edit_script(old: &[String], new: &[String]) -> Vec<(Op, usize, usize)> {
+    let mut matches = vec![];
+    common_lines(old, new, (0, 0), &mut matches);
+
+    let (mut i, mut j) = (0, 0);
+    let mut script = vec![];
+    for (a, b) in matches.into_iter().chain([(old.len(), new.len())]) {
+        script.extend((i..a).map(|i| (Op::Delete, i, j)));
+        script.extend((j..b).map(|j| (Op::Insert, a, j)));
+        if a < old.len() {
+            script.push((Op::Equal, a, b));
+        }
+        (i, j) = (a + 1, b + 1);
+    }
+    script
+}
+
+/// collects the positions of the lines of the longest common subsequence in order,
+/// found with the linear space variant of the diff algorithm of Myers
+fn common_lines(
+    old: &[String],
+    new: &[String],
+    offset: (usize, usize),
+    matches: &mut Vec<(usize, usize)>,
+) {
+    //a common start and end are matched right away, which is all for files with changed lines
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    matches.extend((0..prefix).map(|k| (offset.0 + k, offset.1 + k)));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    let offset = (offset.0 + prefix, offset.1 + prefix);
+
+    //the rest starts and ends with a difference, so the middle snake splits it into smaller parts
+    if !old.is_empty() && !new.is_empty() {
+        let ((x, y), (u, v)) = middle_snake(old, new);
+        common_lines(&old[..x], &new[..y], offset, matches);
+        matches.extend((0..u - x).map(|k| (offset.0 + x + k, offset.1 + y + k)));
+        common_lines(&old[u..], &new[v..], (offset.0 + u, offset.1 + v), matches);
+    }
+    matches.extend((0..suffix).map(|k| (offset.0 + old.len() + k, offset.1 + new.len() + k)));
+}
+
+/// searches the shortest edit script from the start and the end at the same time
+/// and returns the start and end of the diagonal where both searches meet
+fn middle_snake(old: &[String], new: &[String]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    //the furthest x reached on every diagonal k = x - y, backward counts from the end
+    let mut forward = vec![0; 2 * offset as usize + 1];
+    let mut backward = vec![0; 2 * offset as usize + 1];
+    let furthest = |v: &[isize], d: isize, k: isize| {
+        let i = (k + offset) as usize;
+        match k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            true => v[i + 1],
+            false => v[i - 1] + 1,
+        }
+    };
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let start = furthest(&forward, d, k);
+            let mut x = start;
+            while x < n && x - k < m && old[x as usize] == new[(x - k) as usize] {
+                x += 1;
+            }
+            forward[(k + offset) as usize] = x;
+            let other = delta - k;
+            if delta % 2 != 0
+                && (-(d - 1)..=d - 1).contains(&other)
+                && x + backward[(other + offset) as usize] >= n
+            {
+                return (
+                    (start as usize, (start - k) as usize),
+                    (x as usize, (x - k) as usize),
+                );
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let start = furthest(&backward, d, k);
+            let mut x = start;
+            while x < n && x - k < m && old[(n - 1 - x) as usize] == new[(m - 1 - x + k) as usize] {
+                x += 1;
+            }
+            backward[(k + offset) as usize] = x;
+            let other = delta - k;
+            if delta % 2 == 0
+                && (-d..=d).contains(&other)
+                && x + forward[(other + offset) as usize] >= n
+            {
+                return (
+                    ((n - x) as usize, (m - x + k) as usize),
+                    ((n - start) as usize, (m - start + k) as usize),
+                );
+            }
+        }
+    }
+    unreachable!("the searches meet after at most half of all lines")
+}
+
+/// returns for every old line the position of the same line in new, if it was kept
+pub fn line_mapping(old: &[String], new: &[String]) -> Vec<Option<usize>> {
+    let mut mapping = vec![None; old.len()];
+    for (op, i, j) in edit_script(old, new) {
+        if op == Op::Equal {
+            mapping[i] = Some(j);
+        }
+    }
+    mapping
+}
+
+/// creates a unified diff between two files, an empty string means there are no changes
+pub fn unified_diff(old_name: &str, new_name: &str, old: &[String], new: &[String]) -> String {
+    let script = edit_script(old, new);
+    let changes: Vec<usize> = (0..script.len())
+        .filter(|i| script[*i].0 != Op::Equal)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    //group changes that are close enough to share their context
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for change in changes {
+        let start = change.saturating_sub(CONTEXT);
+        let end = (change + CONTEXT + 1).min(script.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let ops = &script[start..end];
+        let old_len = ops.iter().filter(|o| o.0 != Op::Insert).count();
+        let new_len = ops.iter().filter(|o| o.0 != Op::Delete).count();
+        //empty ranges point at the line before them
+        let old_start = ops[0].1 + usize::from(old_len > 0);
+        let new_start = ops[0].2 + usize::from(new_len > 0);
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_len, new_start, new_len
+        ));
+        for (op, i, j) in ops {
+            match op {
+                Op::Equal => out.push_str(&format!(" {}\n", old[*i])),
+                Op::Delete => out.push_str(&format!("-{}\n", old[*i])),
+                Op::Insert => out.push_str(&format!("+{}\n", new[*j])),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let file = lines("a\nb\nc");
+        assert_eq!(super::unified_diff("a", "b", &file, &file), "");
+    }
+
+    #[test]
+    fn test_changed_line() {
+        let old = lines("services:\n  web:\n    image: nginx:1.25\n    ports:\n      - 80:80\n  db:\n    environment:\n      A: b\n    image: postgres:15.4");
+        let mut new = old.clone();
+        new[2] = String::from("    image: nginx:1.27");
+
+        assert_eq!(
+            super::unified_diff("a/docker-compose.yml", "b/docker-compose.yml", &old, &new),
+            "--- a/docker-compose.yml
++++ b/docker-compose.yml
+@@ -1,6 +1,6 @@
+ services:
+   web:
+-    image: nginx:1.25
++    image: nginx:1.27
+     ports:
+       - 80:80
+   db:
+"
+        );
+
+        new[8] = String::from("    image: postgres:15.5");
+        let diff = super::unified_diff("a", "b", &old, &new);
+        assert!(diff.contains("@@ -1,9 +1,9 @@\n"), "{}", diff);
+        assert!(diff.ends_with("-    image: postgres:15.4\n+    image: postgres:15.5\n"));
+    }
+
+    #[test]
+    fn test_inserted_and_removed_lines() {
+        let old = lines("a\nb");
+        let new = lines("a\nb\nc");
+        assert_eq!(
+            super::unified_diff("x", "y", &old, &new),
+            "--- x\n+++ y\n@@ -1,2 +1,3 @@\n a\n b\n+c\n"
+        );
+        assert_eq!(
+            super::unified_diff("x", "y", &lines("a"), &[]),
+            "--- x\n+++ y\n@@ -1,1 +0,0 @@\n-a\n"
+        );
+    }
+
+    #[test]
+    fn test_large_file() {
+        let old: Vec<String> = (0..20000).map(|i| format!("line {}", i)).collect();
+        let mut new = old.clone();
+        new[10000] = String::from("changed");
+        new.insert(5000, String::from("added"));
+        new.remove(15000);
+
+        let mapping = super::line_mapping(&old, &new);
+        assert_eq!(mapping[4999], Some(4999));
+        assert_eq!(mapping[5000], Some(5001));
+        assert_eq!(mapping[10000], None);
+        assert_eq!(mapping[14999], None);
+        assert_eq!(mapping[19999], Some(19999));
+        assert_eq!(mapping.iter().filter(|m| m.is_none()).count(), 2);
+    }
+
+    #[test]
+    fn test_line_mapping() {
+        let old = lines("a\nb\nc\nd");
+        let new = lines("x\na\nc\nd2");
+        assert_eq!(
+            super::line_mapping(&old, &new),
+            vec![Some(1), None, Some(2), None]
+        );
+
+        //lines that moved keep the longest common subsequence
+        let old = lines("a\nb\nc\nd\ne\nf");
+        let new = lines("b\nx\nd\ne\na\nf");
+        assert_eq!(
+            super::line_mapping(&old, &new),
+            vec![None, Some(0), None, Some(2), Some(3), Some(5)]
+        );
+    }
+}
@@ -1 +1,3 @@
+pub mod diff;
 pub mod display_duration_ext;
+pub mod save;
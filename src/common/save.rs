@@ -0,0 +1,200 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// how the lines of a file are terminated, read when a file is opened to write it back the same way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineEndings {
+    crlf: bool,
+    final_newline: bool,
+}
+
+impl Default for LineEndings {
+    fn default() -> Self {
+        Self {
+            crlf: false,
+            final_newline: true,
+        }
+    }
+}
+
+impl LineEndings {
+    /// takes the ending of the first line, an empty file gets the default
+    pub fn detect(content: &str) -> Self {
+        if content.is_empty() {
+            return Self::default();
+        }
+        Self {
+            crlf: content
+                .find('\n')
+                .is_some_and(|i| content[..i].ends_with('\r')),
+            final_newline: content.ends_with('\n'),
+        }
+    }
+
+    /// joins lines that were split with `str::lines`
+    pub fn join(&self, lines: &[String]) -> String {
+        let separator = if self.crlf { "\r\n" } else { "\n" };
+        let mut content = lines.join(separator);
+        if self.final_newline && !lines.is_empty() {
+            content.push_str(separator);
+        }
+        content
+    }
+}
+
+/// the state of a file on disk when it was read or written, to notice changes by someone else
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fingerprint {
+    modified: Option<SystemTime>,
+    /// the hash of the content, none for a missing file
+    hash: Option<u64>,
+}
+
+impl Fingerprint {
+    /// takes the content that was read from or written to the path
+    pub fn new(path: &Path, content: Option<&str>) -> Self {
+        Self {
+            modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+            hash: content.map(hash),
+        }
+    }
+
+    /// checks if the file on disk differs, the content is only compared
+    /// when the modification time changed
+    pub fn changed(&self, path: &Path) -> bool {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified == self.modified && modified.is_some() {
+            return false;
+        }
+        fs::read_to_string(path).ok().as_deref().map(hash) != self.hash
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// the copy of a file that is kept before it is overwritten
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backup {
+    #[default]
+    None,
+    /// `compose.yaml.bak`, replaced on every save
+    Bak,
+    /// `compose.yaml.20240131-120000.bak`
+    Timestamp,
+}
+
+impl Backup {
+    fn path(&self, path: &Path) -> Option<PathBuf> {
+        let mut name = path.file_name()?.to_os_string();
+        match self {
+            Backup::None => return None,
+            Backup::Bak => name.push(".bak"),
+            Backup::Timestamp => {
+                let now = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                name.push(format!(".{}.bak", now));
+            }
+        }
+        Some(path.with_file_name(name))
+    }
+}
+
+/// replaces the content of a file without leaving a half written file behind
+/// the content is written to a temporary file next to it, which gets the permissions
+/// of the old file and is renamed over it, symlinks are followed
+pub fn write_atomic(path: &Path, content: &str, backup: Backup) -> io::Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let permissions = fs::metadata(&target).ok().map(|m| m.permissions());
+    if let (Some(_), Some(backup)) = (&permissions, backup.path(&target)) {
+        fs::copy(&target, backup)?;
+    }
+
+    let mut name = std::ffi::OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temporary = target.with_file_name(name);
+
+    let result = (|| {
+        let mut file = File::create(&temporary)?;
+        file.write_all(content.as_bytes())?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temporary, &target)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backup, LineEndings};
+
+    #[test]
+    fn test_line_endings() {
+        for content in ["a\nb\n", "a\r\nb\r\n", "a\nb", "a\r\nb", "FROM scratch"] {
+            let lines: Vec<String> = content.lines().map(String::from).collect();
+            assert_eq!(LineEndings::detect(content).join(&lines), content);
+        }
+        assert_eq!(
+            LineEndings::detect("").join(&[String::from("A=1")]),
+            "A=1\n"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join(".env");
+        let missing = super::Fingerprint::new(&path, None);
+        assert!(!missing.changed(&path));
+
+        std::fs::write(&path, "A=1\n").unwrap();
+        let written = super::Fingerprint::new(&path, Some("A=1\n"));
+        let changed = (missing.changed(&path), written.changed(&path));
+        //writing the same content again is no change
+        std::fs::write(&path, "A=1\n").unwrap();
+        let rewritten = written.changed(&path);
+        std::fs::write(&path, "A=2\n").unwrap();
+        let modified = written.changed(&path);
+
+        assert_eq!(changed, (true, false));
+        assert!(!rewritten);
+        assert!(modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("compose.yaml");
+        std::fs::write(&path, "old\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        super::write_atomic(&path, "new\n", Backup::Bak).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        let backup = std::fs::read_to_string(dir.join("compose.yaml.bak")).unwrap();
+        let files = std::fs::read_dir(dir).unwrap().count();
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        assert_eq!(content, "new\n");
+        assert_eq!(backup, "old\n");
+        assert_eq!(mode & 0o777, 0o640);
+        //the temporary file was renamed
+        assert_eq!(files, 2);
+    }
+}
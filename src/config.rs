@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::repository::BackendKind;
+
+/// settings read from $XDG_CONFIG_HOME/reel-moby/config.toml
+///
+/// ```toml
+/// [backends]
+/// "registry.example.com" = "distribution"
+/// ```
+#[derive(Deserialize, Default)]
+pub struct Config {
+    /// the backend to use per registry host
+    #[serde(default)]
+    pub backends: HashMap<String, BackendKind>,
+}
+
+impl Config {
+    /// reads the config file, a missing file results in the default config
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Err(_) => Ok(Self::default()),
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| Error::Config(format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    fn path() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("reel-moby").join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::BackendKind;
+
+    #[test]
+    fn test_parse() {
+        let config = super::Config::parse(
+            r#"
+            [backends]
+            "registry.example.com" = "distribution"
+            "docker.io" = "dockerhub"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.backends.get("registry.example.com"),
+            Some(&BackendKind::Distribution)
+        );
+        assert_eq!(
+            config.backends.get("docker.io"),
+            Some(&BackendKind::DockerHub)
+        );
+
+        assert!(super::Config::parse("").unwrap().backends.is_empty());
+        assert!(super::Config::parse("[backends]\n\"ghcr.io\" = \"unknown\"").is_err());
+    }
+}
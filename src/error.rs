@@ -26,6 +26,30 @@ pub enum Error {
     #[error("reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
+    /// neither the given file nor a default docker-compose file could be opened
+    #[error("No docker-compose file found")]
+    NoComposeFile,
+
+    /// a scanned directory contains no file with images
+    #[error("No file with images found in {0}")]
+    NoImageFile(std::path::PathBuf),
+
+    /// a compose file or a file it refers to could not be opened
+    #[error("{0}")]
+    ComposeFile(String),
+
+    /// a service was given that has no image line
+    #[error("No image found for service {0}")]
+    UnknownService(String),
+
+    /// reading or writing a file failed
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// the config file could not be read
+    #[error("Config error: {0}")]
+    Config(String),
+
     /// error while sending to channel
     #[error("sending to channel error: {0}")]
     ChannelSend(#[from] std::sync::mpsc::SendError<crate::ui::UiEvent>),
@@ -0,0 +1,498 @@
+use regex::Regex;
+
+use super::ImageSpan;
+
+/// finds the image of every service in a compose file
+/// lines inside block scalars like `command: |` are skipped
+pub fn find_images(lines: &[String]) -> Vec<ImageSpan> {
+    let mut images = vec![];
+    let mut block_scalar: Option<usize> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let indent = indentation(line);
+        if let Some(block_indent) = block_scalar {
+            if line.trim().is_empty() || indent > block_indent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if starts_block_scalar(line) {
+            block_scalar = Some(indent);
+        }
+        if let Some(mut span) = find_image(line) {
+            span.line = i;
+            images.push(span);
+        }
+    }
+    images
+}
+
+/// finds the value of an `image` key in a single line, the returned span has line 0
+/// the key is either indented in a block mapping or part of a flow mapping like `{ image: nginx }`
+pub fn find_image(line: &str) -> Option<ImageSpan> {
+    lazy_static::lazy_static! {
+        static ref BLOCK: Regex =
+            Regex::new(r#"^(?:[ \t]+(?:-[ \t]+)?|-[ \t]+)(?:image|"image"|'image')[ \t]*:[ \t]*"#)
+                .unwrap();
+        static ref FLOW: Regex =
+            Regex::new(r#"[{,][ \t]*(?:image|"image"|'image')[ \t]*:[ \t]*"#).unwrap();
+    }
+    if let Some(key) = BLOCK.find(line) {
+        return scalar(line, key.end(), false);
+    }
+    FLOW.find_iter(line)
+        .filter(|key| !in_comment_or_quote(line, key.start()))
+        .find_map(|key| scalar(line, key.end(), true))
+}
+
+/// reads the scalar that starts at the given position, quoted or plain
+fn scalar(line: &str, start: usize, flow: bool) -> Option<ImageSpan> {
+    let mut start = start;
+
+    //skip an anchor on the value like `&image nginx`
+    if line[start..].starts_with('&') {
+        let anchor = line[start..].find([' ', '\t'])?;
+        start += anchor;
+        start += line[start..].len() - line[start..].trim_start().len();
+    }
+
+    let rest = &line[start..];
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'');
+    let (start, end) = match quote {
+        Some(quote) => {
+            let len = rest[1..].find(quote)?;
+            (start + 1, start + 1 + len)
+        }
+        None => {
+            let mut end = rest.len();
+            //depth of variables like `${TAG:-1.2}` whose braces don't end a flow mapping
+            let mut variables = 0;
+            for (i, c) in rest.char_indices() {
+                let comment = c == '#' && rest[..i].ends_with([' ', '\t']);
+                match c {
+                    '{' if rest[..i].ends_with('$') => variables += 1,
+                    '}' if variables > 0 => variables -= 1,
+                    _ if comment || (flow && (c == ',' || c == '}')) => {
+                        end = i;
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            (start, start + rest[..end].trim_end().len())
+        }
+    };
+
+    let value = &line[start..end];
+    if value.is_empty()
+        || value.contains([' ', '\t'])
+        || value.starts_with(['*', '|', '>', '{', '['])
+    {
+        return None;
+    }
+    Some(ImageSpan {
+        line: 0,
+        start,
+        end,
+        quote,
+    })
+}
+
+/// splits a line of a block mapping like `  - tag: "1.25"` into the column of the key,
+/// the key and the offset of its value
+pub(super) fn mapping_key(line: &str) -> Option<(usize, &str, usize)> {
+    lazy_static::lazy_static! {
+        static ref KEY: Regex =
+            Regex::new(r#"^((?:[ \t]*-[ \t]+)*[ \t]*)([\w.-]+|"[^"]*"|'[^']*')[ \t]*:(?:[ \t]+|$)"#)
+                .unwrap();
+    }
+    let captures = KEY.captures(line)?;
+    let column = captures.get(1)?.end();
+    let key = captures.get(2)?.as_str().trim_matches(['"', '\'']);
+    Some((column, key, captures.get(0)?.end()))
+}
+
+/// reads the scalar value of a key in a block mapping, the returned span has line 0
+pub(super) fn mapping_value(line: &str, key: &str) -> Option<(usize, ImageSpan)> {
+    let (column, _, start) = mapping_key(line).filter(|(_, found, _)| *found == key)?;
+    scalar(line, start, false).map(|span| (column, span))
+}
+
+/// returns the lines of all keys in the same block mapping as the key of the given line
+/// keys of nested mappings are skipped
+pub(super) fn sibling_keys(lines: &[String], line: usize) -> Vec<usize> {
+    let Some((column, _, _)) = lines.get(line).and_then(|l| mapping_key(l)) else {
+        return vec![];
+    };
+    let is_item = |l: &str| l.trim_start().starts_with("- ");
+
+    let mut start = line;
+    if !is_item(&lines[line]) {
+        for (i, l) in lines[..line].iter().enumerate().rev() {
+            match mapping_key(l) {
+                Some((c, _, _)) if c < column => break,
+                Some((c, _, _)) if c == column => {
+                    start = i;
+                    if is_item(l) {
+                        break;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let mut keys = vec![start];
+    for (i, l) in lines.iter().enumerate().skip(start + 1) {
+        match mapping_key(l) {
+            Some((c, _, _)) if c < column || (c == column && is_item(l)) => break,
+            Some((c, _, _)) if c == column => keys.push(i),
+            _ => (),
+        }
+    }
+    keys
+}
+
+/// returns the `name` of the list item an image belongs to, like a container in a kubernetes manifest
+pub fn item_name(lines: &[String], line: usize) -> Option<String> {
+    let keys = sibling_keys(lines, line);
+    if !lines[*keys.first()?].trim_start().starts_with("- ") {
+        return None;
+    }
+    keys.iter().find_map(|i| {
+        let (_, span) = mapping_value(&lines[*i], "name")?;
+        Some(span.get(&lines[*i]).to_string())
+    })
+}
+
+/// the sections that refer to other files
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Include,
+    Extends,
+}
+
+/// finds the files a compose file refers to with `include:` and `extends: { file: ... }`
+/// the paths are returned as they are written
+pub fn find_references(lines: &[String]) -> Vec<String> {
+    let mut references = vec![];
+    let mut section: Option<(Section, usize)> = None;
+    for line in lines {
+        let content = match line.find(" #") {
+            Some(comment) => &line[..comment],
+            None => line.as_str(),
+        };
+        let trimmed = content.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = indentation(line);
+
+        if let Some((kind, key_indent)) = section {
+            if indent > key_indent {
+                let item = trimmed.strip_prefix('-').map(str::trim_start);
+                let value = match (kind, item) {
+                    (Section::Include, Some(item)) => item.strip_prefix("path:").unwrap_or(item),
+                    (_, _) => trimmed
+                        .strip_prefix(match kind {
+                            Section::Include => "path:",
+                            Section::Extends => "file:",
+                        })
+                        .unwrap_or_default(),
+                };
+                push_reference(&mut references, value);
+                continue;
+            }
+            section = None;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("include:").filter(|_| indent == 0) {
+            match rest.trim().strip_prefix('[') {
+                Some(list) => list
+                    .trim_end_matches(']')
+                    .split(',')
+                    .for_each(|v| push_reference(&mut references, v)),
+                None => section = Some((Section::Include, indent)),
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("extends:") {
+            let rest = rest.trim();
+            match rest.strip_prefix('{') {
+                Some(mapping) => mapping
+                    .trim_end_matches('}')
+                    .split(',')
+                    .filter_map(|entry| entry.trim().strip_prefix("file:"))
+                    .for_each(|v| push_reference(&mut references, v)),
+                //`extends: web` refers to a service of the same file
+                None if rest.is_empty() => section = Some((Section::Extends, indent)),
+                None => (),
+            }
+        }
+    }
+    references
+}
+
+/// adds a path without quotes, values that are no scalar are ignored
+fn push_reference(references: &mut Vec<String>, value: &str) {
+    let value = value.trim().trim_matches(['"', '\'']);
+    if value.is_empty() || value.contains(':') || value.starts_with(['[', '{']) {
+        return;
+    }
+    references.push(value.to_string());
+}
+
+/// checks if a position of a line is part of a comment or a quoted string
+fn in_comment_or_quote(line: &str, position: usize) -> bool {
+    let mut quote: Option<char> = None;
+    for (i, c) in line[..position].char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && (i == 0 || line[..i].ends_with([' ', '\t'])) => return true,
+            None => (),
+        }
+    }
+    quote.is_some()
+}
+
+/// checks if a line ends with the indicator of a literal or folded block like `key: |-`
+fn starts_block_scalar(line: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref REGEX: Regex = Regex::new(r"(?:^|:[ \t]|-[ \t])[ \t]*[|>][-+0-9]*[ \t]*(?:#.*)?$").unwrap();
+    }
+    REGEX.is_match(line)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::ImageSpan;
+
+    fn image(line: &str) -> Option<(&str, &str)> {
+        super::find_image(line).map(|span| (&line[..span.start], span.get(line)))
+    }
+
+    #[test]
+    fn test_find_image_error() {
+        let input: Vec<&str> = vec![
+            "",
+            "version: '2'",
+            "image: ",
+            "  image: ",
+            "  image: nginx:1.25 and more",
+            "  # image: nginx",
+            "  image: \"nginx",
+            "  image: *alias",
+            "  image: |",
+            "  command: [\"image: nginx\"]",
+            "  command: echo # {image: nginx}",
+        ];
+        for i in input {
+            assert_eq!(super::find_image(i), None, "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_find_image() {
+        let input: Vec<(&str, (&str, &str))> = vec![
+            ("  image: nginx", ("  image: ", "nginx")),
+            ("  image: library/nginx", ("  image: ", "library/nginx")),
+            (
+                "  image: ghcr.io/library/nginx",
+                ("  image: ", "ghcr.io/library/nginx"),
+            ),
+            ("  image: nginx # comment", ("  image: ", "nginx")),
+            ("  image: test-hyphen", ("  image: ", "test-hyphen")),
+            ("  image: test.dot", ("  image: ", "test.dot")),
+            (
+                "  image: nginx:1.25@sha256:0123abcd",
+                ("  image: ", "nginx:1.25@sha256:0123abcd"),
+            ),
+            ("  image: \"nginx:1.25\"", ("  image: \"", "nginx:1.25")),
+            (
+                "  image: 'nginx:1.25' # pinned",
+                ("  image: '", "nginx:1.25"),
+            ),
+            ("\timage:\tnginx:1.25", ("\timage:\t", "nginx:1.25")),
+            ("  \"image\": nginx", ("  \"image\": ", "nginx")),
+            ("  image: &img nginx:1.25", ("  image: &img ", "nginx:1.25")),
+            ("  - image: nginx", ("  - image: ", "nginx")),
+            (
+                "  web: { image: nginx:1.25, ports: [80] }",
+                ("  web: { image: ", "nginx:1.25"),
+            ),
+            (
+                "  web: {ports: [80], image: \"nginx:1.25\"}",
+                ("  web: {ports: [80], image: \"", "nginx:1.25"),
+            ),
+            ("  web: {image: nginx}", ("  web: {image: ", "nginx")),
+            (
+                "  image: myorg/app:${APP_VERSION:-1.4.2}",
+                ("  image: ", "myorg/app:${APP_VERSION:-1.4.2}"),
+            ),
+            (
+                "  web: {image: app:${TAG:-${DEFAULT}}, ports: [80]}",
+                ("  web: {image: ", "app:${TAG:-${DEFAULT}}"),
+            ),
+        ];
+
+        for i in input {
+            assert_eq!(image(i.0), Some(i.1), "{}", i.0);
+        }
+    }
+
+    #[test]
+    fn test_replace() {
+        let input: Vec<(&str, &str)> = vec![
+            ("  image: nginx", "  image: nginx:1.27"),
+            ("  image: nginx # comment", "  image: nginx:1.27 # comment"),
+            (
+                "  image: nginx:1.25@sha256:0123abcd # pinned",
+                "  image: nginx:1.27 # pinned",
+            ),
+            (
+                "  image: \"nginx:1.25\"  # quoted",
+                "  image: \"nginx:1.27\"  # quoted",
+            ),
+            (
+                "  web: {image: 'nginx', ports: [80]}",
+                "  web: {image: 'nginx:1.27', ports: [80]}",
+            ),
+        ];
+
+        for i in input {
+            let span = super::find_image(i.0).unwrap();
+            assert_eq!(span.replace(i.0, "nginx:1.27"), i.1);
+        }
+    }
+
+    #[test]
+    fn test_find_references() {
+        let lines: Vec<String> = [
+            "include:",
+            "  - db/compose.yaml # the database",
+            "  - path: ./cache.yaml",
+            "    project_directory: ./cache",
+            "  - path:",
+            "      - 'monitoring.yaml'",
+            "      - monitoring.override.yaml",
+            "services:",
+            "  web:",
+            "    extends:",
+            "      file: common.yml",
+            "      service: web",
+            "  worker:",
+            "    extends: { file: \"common/worker.yml\", service: worker }",
+            "  other:",
+            "    extends: web",
+            "    image: nginx",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+        assert_eq!(
+            super::find_references(&lines),
+            vec![
+                "db/compose.yaml",
+                "./cache.yaml",
+                "monitoring.yaml",
+                "monitoring.override.yaml",
+                "common.yml",
+                "common/worker.yml",
+            ]
+        );
+
+        let lines = vec![String::from("include: [a.yaml, 'b.yaml']")];
+        assert_eq!(super::find_references(&lines), vec!["a.yaml", "b.yaml"]);
+    }
+
+    #[test]
+    fn test_find_images() {
+        let lines: Vec<String> = [
+            "x-base: &base",
+            "  image: busybox:1.36",
+            "services:",
+            "  web:",
+            "    command: |",
+            "      image: not-an-image",
+            "",
+            "      echo",
+            "    image: 'nginx:1.25'",
+            "  db: { image: postgres:15.4 }",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+        assert_eq!(
+            super::find_images(&lines),
+            vec![
+                ImageSpan {
+                    line: 1,
+                    start: 9,
+                    end: 21,
+                    quote: None
+                },
+                ImageSpan {
+                    line: 8,
+                    start: 12,
+                    end: 22,
+                    quote: Some('\'')
+                },
+                ImageSpan {
+                    line: 9,
+                    start: 15,
+                    end: 28,
+                    quote: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kubernetes() {
+        let lines: Vec<String> = [
+            "apiVersion: apps/v1",
+            "kind: Deployment",
+            "spec:",
+            "  template:",
+            "    spec:",
+            "      initContainers:",
+            "      - image: busybox:1.36",
+            "        name: init",
+            "      containers:",
+            "        - name: web",
+            "          ports:",
+            "            - containerPort: 80",
+            "          image: nginx:1.25",
+            "---",
+            "apiVersion: v1",
+            "kind: Pod",
+            "spec:",
+            "  containers:",
+            "  - image: \"redis:7.2\"",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+        let images: Vec<(usize, &str, Option<String>)> = super::find_images(&lines)
+            .iter()
+            .map(|span| {
+                let name = super::item_name(&lines, span.line);
+                (span.line, span.get(&lines[span.line]), name)
+            })
+            .collect();
+        assert_eq!(
+            images,
+            vec![
+                (6, "busybox:1.36", Some(String::from("init"))),
+                (12, "nginx:1.25", Some(String::from("web"))),
+                (18, "redis:7.2", None),
+            ]
+        );
+    }
+}
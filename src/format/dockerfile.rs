@@ -0,0 +1,120 @@
+use regex::Regex;
+
+use super::ImageSpan;
+
+lazy_static::lazy_static! {
+    /// `FROM [--flag=value ...] image [AS name]`
+    static ref FROM: Regex = Regex::new(
+        r"(?i)^[ \t]*FROM[ \t]+((?:--[^ \t]+[ \t]+)*)([^ \t]+)(?:[ \t]+AS[ \t]+([^ \t]+))?[ \t]*$"
+    )
+    .unwrap();
+}
+
+/// finds the base images of all stages of a Dockerfile
+/// references to earlier stages like `FROM builder`, `scratch` and images with build args are skipped
+pub fn find_images(lines: &[String]) -> Vec<ImageSpan> {
+    let mut stages: Vec<String> = vec![];
+    let mut images = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let Some(caps) = FROM.captures(line) else {
+            continue;
+        };
+        let image = caps.get(2).expect("image is not optional");
+        let name = image.as_str().to_lowercase();
+        let skip = name == "scratch" || name.contains('$') || stages.contains(&name);
+        if let Some(stage) = caps.get(3) {
+            stages.push(stage.as_str().to_lowercase());
+        }
+        if skip {
+            continue;
+        }
+        images.push(ImageSpan {
+            line: i,
+            start: image.start(),
+            end: image.end(),
+            quote: None,
+        });
+    }
+    images
+}
+
+/// returns the name of the stage a `FROM` line starts
+pub fn stage_name(line: &str) -> Option<String> {
+    FROM.captures(line)?
+        .get(3)
+        .map(|stage| stage.as_str().to_string())
+}
+
+/// checks if a file name belongs to a Dockerfile like `Dockerfile.dev` or `app.dockerfile`
+pub fn is_dockerfile(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "dockerfile"
+        || name == "containerfile"
+        || name.starts_with("dockerfile.")
+        || name.starts_with("containerfile.")
+        || name.ends_with(".dockerfile")
+        || name.ends_with(".containerfile")
+}
+
+#[cfg(test)]
+mod tests {
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_find_images() {
+        let lines = lines(
+            "# syntax=docker/dockerfile:1
+ARG BASE=alpine:3.19
+FROM --platform=$BUILDPLATFORM golang:1.22-alpine AS builder
+RUN go build
+FROM builder AS test
+FROM ${BASE}
+from node:20@sha256:0123abcd as Assets
+FROM scratch
+FROM gcr.io/distroless/static:nonroot
+COPY --from=builder /app /app
+FROM Assets",
+        );
+
+        let images: Vec<(usize, &str)> = super::find_images(&lines)
+            .iter()
+            .map(|span| (span.line, span.get(&lines[span.line])))
+            .collect();
+        assert_eq!(
+            images,
+            vec![
+                (2, "golang:1.22-alpine"),
+                (6, "node:20@sha256:0123abcd"),
+                (8, "gcr.io/distroless/static:nonroot"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace() {
+        let line = "FROM --platform=linux/amd64 golang:1.22-alpine AS builder";
+        let span = super::find_images(&[line.to_string()])[0];
+        assert_eq!(
+            span.replace(line, "golang:1.23-alpine"),
+            "FROM --platform=linux/amd64 golang:1.23-alpine AS builder"
+        );
+        assert_eq!(super::stage_name(line), Some(String::from("builder")));
+    }
+
+    #[test]
+    fn test_is_dockerfile() {
+        for name in [
+            "Dockerfile",
+            "Dockerfile.dev",
+            "app.dockerfile",
+            "Containerfile",
+        ] {
+            assert!(super::is_dockerfile(name), "{}", name);
+        }
+        for name in ["compose.yaml", "Dockerfile-notes.md", "dockerfiles"] {
+            assert!(!super::is_dockerfile(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::path::{Path, PathBuf};
+
+use crate::common::diff;
+use crate::common::save::{self, Backup, Fingerprint, LineEndings};
+
+/// a variable expression like `$VAR`, `${VAR}` or `${VAR:-default}` inside a value
+#[derive(Debug, PartialEq)]
+struct Expression {
+    /// byte offset of the `$`
+    start: usize,
+    /// byte offset behind the expression
+    end: usize,
+    name: String,
+    /// the operator like `:-` and its argument
+    modifier: Option<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Unclosed(String),
+    Required(String, String),
+    NoVariable(String),
+    NoMatch(String, String),
+    FromEnvironment(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unclosed(s) => write!(f, "Unclosed variable in {}", s),
+            Error::Required(name, message) => {
+                write!(f, "Variable {} is required: {}", name, message)
+            }
+            Error::NoVariable(s) => write!(f, "{} contains no variable", s),
+            Error::NoMatch(s, new) => write!(f, "{} can't be expressed with {}", new, s),
+            Error::FromEnvironment(name) => {
+                write!(f, "{} is set in the environment and can't be changed", name)
+            }
+        }
+    }
+}
+
+/// checks if a value contains variables that need to be resolved
+pub fn has_variables(input: &str) -> bool {
+    expressions(input).map(|e| !e.is_empty()).unwrap_or(true)
+}
+
+fn expressions(input: &str) -> Result<Vec<Expression>, Error> {
+    let bytes = input.as_bytes();
+    let is_name = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'$' || i + 1 == bytes.len() {
+            i += 1;
+            continue;
+        }
+        match bytes[i + 1] {
+            //escaped dollar
+            b'$' => i += 2,
+            b'{' => {
+                let mut depth = 0;
+                let mut close = None;
+                for (j, b) in bytes.iter().enumerate().skip(i + 1) {
+                    match b {
+                        b'{' => depth += 1,
+                        b'}' if depth == 1 => {
+                            close = Some(j);
+                            break;
+                        }
+                        b'}' => depth -= 1,
+                        _ => (),
+                    }
+                }
+                let close = close.ok_or(Error::Unclosed(input.to_string()))?;
+                let inner = &input[i + 2..close];
+                let name_len = inner.bytes().take_while(|b| is_name(*b)).count();
+                let rest = &inner[name_len..];
+                let modifier = [":-", ":?", ":+", "-", "?", "+"]
+                    .iter()
+                    .find(|op| rest.starts_with(**op))
+                    .map(|op| (op.to_string(), rest[op.len()..].to_string()));
+                result.push(Expression {
+                    start: i,
+                    end: close + 1,
+                    name: inner[..name_len].to_string(),
+                    modifier,
+                });
+                i = close + 1;
+            }
+            b if is_name(b) => {
+                let len = bytes[i + 1..].iter().take_while(|b| is_name(**b)).count();
+                result.push(Expression {
+                    start: i,
+                    end: i + 1 + len,
+                    name: input[i + 1..i + 1 + len].to_string(),
+                    modifier: None,
+                });
+                i += 1 + len;
+            }
+            _ => i += 1,
+        }
+    }
+    Ok(result)
+}
+
+/// resolves an expression with the given variables
+fn evaluate(
+    expression: &Expression,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, Error> {
+    let value = lookup(&expression.name);
+    let Some((op, argument)) = &expression.modifier else {
+        return Ok(value.unwrap_or_default());
+    };
+    let set = match op.starts_with(':') {
+        true => value.as_ref().is_some_and(|v| !v.is_empty()),
+        false => value.is_some(),
+    };
+    match (op.trim_start_matches(':'), set) {
+        ("-", true) | ("?", true) => Ok(value.unwrap_or_default()),
+        ("-", false) => resolve(argument, lookup),
+        ("?", false) => Err(Error::Required(
+            expression.name.clone(),
+            resolve(argument, lookup)?,
+        )),
+        ("+", true) => resolve(argument, lookup),
+        _ => Ok(String::new()),
+    }
+}
+
+/// replaces all variables of a value, `$$` stands for a single `$`
+pub fn resolve(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut last = 0;
+    for expression in expressions(input)? {
+        result.push_str(&input[last..expression.start].replace("$$", "$"));
+        result.push_str(&evaluate(&expression, lookup)?);
+        last = expression.end;
+    }
+    result.push_str(&input[last..].replace("$$", "$"));
+    Ok(result)
+}
+
+/// finds the variable that has to be changed so that the value resolves to new_value
+/// returns the name of the variable and its new value
+/// the last variable is preferred as it usually holds the tag
+pub fn assign(
+    input: &str,
+    new_value: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(String, String), Error> {
+    let expressions = expressions(input)?;
+    if expressions.is_empty() {
+        return Err(Error::NoVariable(input.to_string()));
+    }
+    for (i, expression) in expressions.iter().enumerate().rev() {
+        if expression.name.is_empty() {
+            continue;
+        }
+        let prefix = resolve(&input[..expression.start], lookup)?;
+        let suffix = resolve(&input[expression.end..], lookup)?;
+        let middle = new_value
+            .strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.strip_suffix(suffix.as_str()));
+        match middle {
+            Some(middle) if !middle.is_empty() => {
+                return Ok((expressions[i].name.clone(), middle.to_string()))
+            }
+            _ => continue,
+        }
+    }
+    Err(Error::NoMatch(input.to_string(), new_value.to_string()))
+}
+
+/// a .env file next to a compose file, lines that are not changed are kept as they are
+pub struct EnvFile {
+    path: PathBuf,
+    lines: Vec<String>,
+    endings: LineEndings,
+    fingerprint: Fingerprint,
+    /// the variables that were set since the file was read or saved
+    assigned: Vec<(String, String)>,
+}
+
+impl EnvFile {
+    /// reads the .env file of a directory, a missing file is treated as empty
+    pub fn load(dir: &Path) -> Self {
+        Self::read(dir.join(".env"))
+    }
+
+    fn read(path: PathBuf) -> Self {
+        let content = std::fs::read_to_string(&path).ok();
+        let text = content.as_deref().unwrap_or_default();
+        Self {
+            lines: text.lines().map(String::from).collect(),
+            endings: LineEndings::detect(text),
+            fingerprint: Fingerprint::new(&path, content.as_deref()),
+            path,
+            assigned: vec![],
+        }
+    }
+
+    /// splits a line into the part up to the value, the value and its quote
+    fn split(line: &str) -> Option<(&str, &str, &str, Option<char>)> {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('#') {
+            return None;
+        }
+        let (front, value) = line.split_once('=')?;
+        let key = front.trim().trim_start_matches("export ").trim();
+        let value_start = front.len() + 1;
+        let raw = value.trim_start();
+        let front = &line[..value_start + value.len() - raw.len()];
+        let quote = raw.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let value = match quote {
+            Some(q) => raw[1..].split(q).next().unwrap_or_default(),
+            None => match raw.find(" #") {
+                Some(comment) => raw[..comment].trim_end(),
+                None => raw.trim_end(),
+            },
+        };
+        Some((key, front, value, quote))
+    }
+
+    /// returns the value of a variable
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.lines
+            .iter()
+            .rev()
+            .filter_map(|l| Self::split(l))
+            .find(|(key, _, _, _)| *key == name)
+            .map(|(_, _, value, _)| value.to_string())
+    }
+
+    /// sets the value of a variable, an existing line keeps its quotes and comment
+    pub fn set(&mut self, name: &str, value: &str) {
+        let position = self
+            .lines
+            .iter()
+            .rposition(|l| Self::split(l).is_some_and(|(key, _, _, _)| key == name));
+        match position {
+            None => self.lines.push(format!("{}={}", name, value)),
+            Some(i) => {
+                let line = &self.lines[i];
+                let (_, front, old, quote) = Self::split(line).expect("line was matched before");
+                let quote_len = quote.map(|_| 1).unwrap_or_default();
+                let rest = &line[front.len() + quote_len + old.len()..];
+                self.lines[i] = format!(
+                    "{}{}{}{}",
+                    front,
+                    quote.map(String::from).unwrap_or_default(),
+                    value,
+                    rest
+                );
+            }
+        }
+        self.assigned.retain(|(key, _)| key != name);
+        self.assigned.push((name.to_string(), value.to_string()));
+    }
+
+    /// checks if there are unsaved changes
+    pub fn changed(&self) -> bool {
+        !self.assigned.is_empty()
+    }
+
+    /// returns a unified diff between the file on disk and the lines in memory
+    pub fn diff(&self) -> String {
+        if !self.changed() {
+            return String::new();
+        }
+        let on_disk: Vec<String> = match std::fs::read_to_string(&self.path) {
+            Err(_) => vec![],
+            Ok(content) => content.lines().map(String::from).collect(),
+        };
+        let name = self.path.display().to_string();
+        diff::unified_diff(&name, &name, &on_disk, &self.lines)
+    }
+
+    /// writes the file if it was changed
+    pub fn save(&mut self, backup: Backup) -> Result<(), std::io::Error> {
+        if !self.changed() {
+            return Ok(());
+        }
+        let content = self.endings.join(&self.lines);
+        save::write_atomic(&self.path, &content, backup)?;
+        self.fingerprint = Fingerprint::new(&self.path, Some(&content));
+        self.assigned.clear();
+        Ok(())
+    }
+
+    /// the path and the state on disk when the file was read or saved
+    pub fn fingerprint(&self) -> (&Path, Fingerprint) {
+        (&self.path, self.fingerprint)
+    }
+
+    /// checks if the file was changed by someone else since it was read or saved
+    pub fn modified_on_disk(&self) -> bool {
+        self.fingerprint.changed(&self.path)
+    }
+
+    /// reads the file again, unsaved changes are dropped
+    pub fn reload(&mut self) {
+        *self = Self::read(self.path.clone());
+    }
+
+    /// reads the file again and sets the variables that were set since the last save
+    pub fn merge(&mut self) {
+        let assigned = std::mem::take(&mut self.assigned);
+        *self = Self::read(self.path.clone());
+        for (name, value) in assigned {
+            self.set(&name, &value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use super::{EnvFile, Error, Fingerprint, LineEndings};
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let vars = lookup(&[("TAG", "1.5"), ("EMPTY", ""), ("REG", "ghcr.io")]);
+        let input: Vec<(&str, &str)> = vec![
+            ("nginx:1.25", "nginx:1.25"),
+            ("myorg/app:${TAG}", "myorg/app:1.5"),
+            ("myorg/app:$TAG", "myorg/app:1.5"),
+            ("myorg/app:${MISSING:-1.4.2}", "myorg/app:1.4.2"),
+            ("myorg/app:${TAG:-1.4.2}", "myorg/app:1.5"),
+            ("myorg/app:${EMPTY:-1.4.2}", "myorg/app:1.4.2"),
+            ("myorg/app:${EMPTY-1.4.2}", "myorg/app:"),
+            ("myorg/app:${MISSING:-${TAG}}", "myorg/app:1.5"),
+            ("${REG}/app:${TAG:+v}${TAG}", "ghcr.io/app:v1.5"),
+            ("app:$$TAG", "app:$TAG"),
+        ];
+        for i in input {
+            assert_eq!(super::resolve(i.0, &vars), Ok(String::from(i.1)), "{}", i.0);
+        }
+
+        assert!(matches!(
+            super::resolve("app:${MISSING:?set it}", &vars),
+            Err(Error::Required(_, _))
+        ));
+        assert!(matches!(
+            super::resolve("app:${TAG", &vars),
+            Err(Error::Unclosed(_))
+        ));
+    }
+
+    #[test]
+    fn test_assign() {
+        let vars = lookup(&[("REG", "ghcr.io"), ("TAG", "1.4.2")]);
+        let input: Vec<(&str, &str, (&str, &str))> = vec![
+            (
+                "myorg/app:${APP_VERSION:-1.4.2}",
+                "myorg/app:1.5.0",
+                ("APP_VERSION", "1.5.0"),
+            ),
+            ("${REG}/app:${TAG}", "ghcr.io/app:1.5.0", ("TAG", "1.5.0")),
+            (
+                "${REG}/app:v${TAG}-alpine",
+                "ghcr.io/app:v1.5.0-alpine",
+                ("TAG", "1.5.0"),
+            ),
+            ("${IMAGE}", "nginx:1.27", ("IMAGE", "nginx:1.27")),
+        ];
+        for i in input {
+            assert_eq!(
+                super::assign(i.0, i.1, &vars),
+                Ok((String::from(i.2 .0), String::from(i.2 .1))),
+                "{}",
+                i.0
+            );
+        }
+
+        assert!(super::assign("nginx:1.25", "nginx:1.27", &vars).is_err());
+        assert!(super::assign("${REG}/app:${TAG}", "quay.io/app:1.5.0", &vars).is_err());
+    }
+
+    #[test]
+    fn test_env_file() {
+        let mut env = EnvFile {
+            path: std::path::PathBuf::from(".env"),
+            lines: [
+                "# versions",
+                "APP_VERSION=1.4.2 # pinned",
+                "export DB_VERSION=\"15.4\"",
+                "EMPTY=",
+            ]
+            .iter()
+            .map(|l| l.to_string())
+            .collect(),
+            endings: LineEndings::default(),
+            fingerprint: Fingerprint::new(Path::new("/nonexistent/.env"), None),
+            assigned: vec![],
+        };
+
+        assert_eq!(env.get("APP_VERSION"), Some(String::from("1.4.2")));
+        assert_eq!(env.get("DB_VERSION"), Some(String::from("15.4")));
+        assert_eq!(env.get("EMPTY"), Some(String::new()));
+        assert_eq!(env.get("versions"), None);
+
+        env.set("APP_VERSION", "1.5.0");
+        env.set("DB_VERSION", "16.1");
+        env.set("NEW", "1");
+        assert!(env.changed());
+        assert_eq!(
+            env.lines,
+            [
+                "# versions",
+                "APP_VERSION=1.5.0 # pinned",
+                "export DB_VERSION=\"16.1\"",
+                "EMPTY=",
+                "NEW=1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use super::compose::{mapping_key, mapping_value, sibling_keys};
+use super::{Error, ImageSpan};
+use crate::repo::{self, Reference};
+
+/// an image that is split into several keys like in the values of a helm chart
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SplitImage {
+    pub registry: Option<ImageSpan>,
+    pub repository: ImageSpan,
+    pub tag: ImageSpan,
+    /// a `digest` key, which may be empty
+    pub digest: Option<ImageSpan>,
+}
+
+impl SplitImage {
+    /// joins the keys to a reference like `docker.io/bitnami/nginx:1.25`
+    pub fn get(&self, lines: &[String]) -> String {
+        let get = |span: &ImageSpan| span.get(&lines[span.line]).to_string();
+        let mut reference = match &self.registry {
+            Some(registry) => format!("{}/{}", get(registry), get(&self.repository)),
+            None => get(&self.repository),
+        };
+        reference.push(':');
+        reference.push_str(&get(&self.tag));
+        if let Some(digest) = self.digest.as_ref().map(get).filter(|d| !d.is_empty()) {
+            reference.push('@');
+            reference.push_str(&digest);
+        }
+        reference
+    }
+
+    /// writes a reference into the keys, the repository is only written when it changes
+    /// a digest needs a `digest` key, which is emptied when the reference has no digest
+    pub fn set(&self, lines: &mut [String], reference: &str) -> Result<(), Error> {
+        let (name, tag) = match repo::split_tag_from_repo(reference) {
+            Ok((name, tag)) if !tag.is_empty() => (name, tag),
+            _ => return Err(Error::NoImage),
+        };
+        let (_, digest) = repo::split_digest_from_repo(reference);
+        if digest.is_some() && self.digest.is_none() {
+            return Err(Error::NoDigestKey);
+        }
+
+        let current = self.get(lines);
+        let (current, _) = repo::split_tag_from_repo(&current).map_err(|_| Error::NoImage)?;
+        let same = |a: &str, b: &str| match (Reference::parse(a), Reference::parse(b)) {
+            (Ok(a), Ok(b)) => a.name() == b.name(),
+            _ => a == b,
+        };
+        if !same(current, name) {
+            match &self.registry {
+                None => write(lines, &self.repository, name),
+                Some(registry) => {
+                    let (domain, path) = match name.split_once('/') {
+                        Some((first, rest))
+                            if first.contains(['.', ':']) || first == "localhost" =>
+                        {
+                            (first, rest)
+                        }
+                        _ => ("docker.io", name),
+                    };
+                    write(lines, registry, domain);
+                    write(lines, &self.repository, path);
+                }
+            }
+        }
+        write(lines, &self.tag, tag);
+        if let Some(span) = &self.digest {
+            write(lines, span, digest.unwrap_or_default());
+        }
+        Ok(())
+    }
+}
+
+/// replaces the value of a key, an empty value is written as `""`
+fn write(lines: &mut [String], span: &ImageSpan, value: &str) {
+    let line = &mut lines[span.line];
+    let value = match (span.quote, value.is_empty()) {
+        (None, true) => "\"\"",
+        _ => value,
+    };
+    //a key without a value like `digest:` needs a space before the new value
+    let space = match span.quote.is_none()
+        && span.start == span.end
+        && !line[..span.start].ends_with([' ', '\t'])
+    {
+        true => " ",
+        false => "",
+    };
+    *line = span.replace(line, &format!("{}{}", space, value));
+}
+
+/// reads a value that may be empty like `digest: ""` or `digest:`, the returned span has line 0
+fn optional_value(line: &str, key: &str) -> Option<ImageSpan> {
+    if let Some((_, span)) = mapping_value(line, key) {
+        return Some(span);
+    }
+    let (_, _, start) = mapping_key(line).filter(|(_, found, _)| *found == key)?;
+    let rest = &line[start..];
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'');
+    match quote {
+        Some(q) if rest[1..].starts_with(q) => Some(ImageSpan {
+            line: 0,
+            start: start + 1,
+            end: start + 1,
+            quote,
+        }),
+        None if rest.trim().is_empty() || rest.starts_with('#') => Some(ImageSpan {
+            line: 0,
+            start,
+            end: start,
+            quote: None,
+        }),
+        _ => None,
+    }
+}
+
+/// finds mappings with a `repository` and a `tag` key
+/// mappings without a tag like `tag: ""` are skipped, because the tag is not known
+pub fn find_images(lines: &[String]) -> Vec<SplitImage> {
+    let mut images = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let Some((_, mut repository)) = mapping_value(line, "repository") else {
+            continue;
+        };
+        repository.line = i;
+
+        let value = |key: &str| {
+            sibling_keys(lines, i).into_iter().find_map(|j| {
+                let mut span = optional_value(&lines[j], key)?;
+                span.line = j;
+                Some(span)
+            })
+        };
+        let Some(tag) = value("tag").filter(|tag| tag.start < tag.end) else {
+            continue;
+        };
+        images.push(SplitImage {
+            registry: value("registry").filter(|registry| registry.start < registry.end),
+            repository,
+            tag,
+            digest: value("digest"),
+        });
+    }
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_find_images() {
+        let lines = lines(
+            r#"image:
+  registry: docker.io
+  repository: bitnami/nginx
+  # the tag of the image
+  tag: "1.25.3" # pinned
+  pullPolicy: IfNotPresent
+sidecar:
+  tag: 0.4
+  resources:
+    repository: none
+  repository: prom/statsd-exporter
+default:
+  repository: nginx
+  tag: ""
+extraImages:
+  - repository: redis
+    tag: 7.2
+  - tag: 16
+    repository: postgres"#,
+        );
+
+        let images: Vec<(usize, String)> = super::find_images(&lines)
+            .iter()
+            .map(|image| (image.repository.line, image.get(&lines)))
+            .collect();
+        assert_eq!(
+            images,
+            vec![
+                (2, String::from("docker.io/bitnami/nginx:1.25.3")),
+                (10, String::from("prom/statsd-exporter:0.4")),
+                (15, String::from("redis:7.2")),
+                (18, String::from("postgres:16")),
+            ]
+        );
+
+        let tag = super::find_images(&lines)[0].tag;
+        assert_eq!(
+            tag.replace(&lines[tag.line], "1.27.0"),
+            r#"  tag: "1.27.0" # pinned"#
+        );
+    }
+}
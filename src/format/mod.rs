@@ -0,0 +1,52 @@
+pub mod compose;
+pub mod dockerfile;
+pub mod env;
+pub mod helm;
+pub mod opened_file;
+pub mod quadlet;
+pub mod scan;
+
+/// errors while reading or changing the images of a file
+#[derive(Debug)]
+pub enum Error {
+    NoImage,
+    Variable(env::Error),
+    /// a digest can't be written to an image that is split into keys without a `digest` key
+    NoDigestKey,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NoImage => write!(f, "Line contains no image"),
+            Error::Variable(e) => write!(f, "{}", e),
+            Error::NoDigestKey => write!(f, "Image has no digest key to pin the digest"),
+        }
+    }
+}
+
+/// the position of an image reference inside a line of a file
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ImageSpan {
+    /// the line number, starting at 0
+    pub line: usize,
+    /// byte offset of the first character of the reference, behind an opening quote
+    pub start: usize,
+    /// byte offset behind the last character of the reference
+    pub end: usize,
+    /// the quote that encloses the reference
+    pub quote: Option<char>,
+}
+
+impl ImageSpan {
+    /// returns the reference inside the given line
+    pub fn get<'a>(&self, line: &'a str) -> &'a str {
+        &line[self.start..self.end]
+    }
+
+    /// replaces the reference inside the given line and keeps everything around it
+    /// like quotes and trailing comments
+    pub fn replace(&self, line: &str, reference: &str) -> String {
+        format!("{}{}{}", &line[..self.start], reference, &line[self.end..])
+    }
+}
@@ -0,0 +1,602 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::env::{self, EnvFile};
+use super::helm::{self, SplitImage};
+use super::{compose, dockerfile, quadlet, Error, ImageSpan};
+use crate::common::diff;
+use crate::common::save::{self, Backup, Fingerprint, LineEndings};
+use crate::repo::{self, Reference};
+
+/// the formats a file with image references can have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Compose,
+    Dockerfile,
+    Quadlet,
+}
+
+impl Kind {
+    /// guesses the format from the file name, everything unknown is treated as compose file
+    pub fn of(path: &Path) -> Self {
+        Self::detect(path).unwrap_or(Kind::Compose)
+    }
+
+    /// returns the format of files that may contain images, yaml files are read as compose files
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        match name {
+            _ if dockerfile::is_dockerfile(name) => Some(Kind::Dockerfile),
+            _ if quadlet::is_quadlet(name) => Some(Kind::Quadlet),
+            _ if name.ends_with(".yaml") || name.ends_with(".yml") => Some(Kind::Compose),
+            _ => None,
+        }
+    }
+}
+
+/// an image reference that is written in one place or split into several keys
+enum Image {
+    Inline(ImageSpan),
+    Split(SplitImage),
+}
+
+impl Image {
+    /// the line the image is shown at
+    fn line(&self) -> usize {
+        match self {
+            Image::Inline(span) => span.line,
+            Image::Split(split) => split.repository.line,
+        }
+    }
+}
+
+/// a file with image references that is kept in memory until it is saved
+pub struct OpenedFile {
+    path: PathBuf,
+    kind: Kind,
+    lines: Vec<String>,
+    /// the lines as they were read or saved, to merge changes that were made on disk
+    original: Vec<String>,
+    endings: LineEndings,
+    fingerprint: Fingerprint,
+    changed: bool,
+    /// the variables of the .env file next to the file, shared with the other files of the directory
+    env: Arc<Mutex<EnvFile>>,
+}
+
+impl OpenedFile {
+    pub fn open(path: &Path) -> Result<Self, std::io::Error> {
+        let env = EnvFile::load(path.parent().unwrap_or(Path::new("")));
+        Self::open_with_env(path, Arc::new(Mutex::new(env)))
+    }
+
+    /// opens a file that uses the given .env file, which is shared with the other files
+    /// of the directory, so that a variable changed in one file is seen by all of them
+    pub fn open_with_env(path: &Path, env: Arc<Mutex<EnvFile>>) -> Result<Self, std::io::Error> {
+        let content = std::fs::read_to_string(path)?;
+        let mut file = Self::with_lines(path, content.lines().map(String::from).collect(), env);
+        file.endings = LineEndings::detect(&content);
+        file.fingerprint = Fingerprint::new(path, Some(&content));
+        Ok(file)
+    }
+
+    fn with_lines(path: &Path, lines: Vec<String>, env: Arc<Mutex<EnvFile>>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            kind: Kind::of(path),
+            original: lines.clone(),
+            lines,
+            endings: LineEndings::default(),
+            fingerprint: Fingerprint::new(path, None),
+            changed: false,
+            env,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// checks if there are unsaved changes
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+
+    /// the .env file next to the file
+    pub fn env(&self) -> &Arc<Mutex<EnvFile>> {
+        &self.env
+    }
+
+    /// returns the files this file refers to with `include:` or `extends:`
+    /// variables in the paths are resolved and relative paths are resolved against the directory of this file
+    pub fn references(&self) -> Result<Vec<PathBuf>, Error> {
+        if self.kind != Kind::Compose {
+            return Ok(vec![]);
+        }
+        let dir = self.path.parent().unwrap_or(Path::new(""));
+        let env = self.env.lock().unwrap();
+        compose::find_references(&self.lines)
+            .iter()
+            .map(|r| {
+                let path =
+                    env::resolve(r, &|name| variable(&env, name)).map_err(Error::Variable)?;
+                Ok(dir.join(path))
+            })
+            .collect()
+    }
+
+    /// returns the lines of all images
+    pub fn image_lines(&self) -> Vec<usize> {
+        self.images().iter().map(Image::line).collect()
+    }
+
+    /// returns all images ordered by line
+    fn images(&self) -> Vec<Image> {
+        let mut images: Vec<Image> = match self.kind {
+            Kind::Compose => compose::find_images(&self.lines),
+            Kind::Dockerfile => dockerfile::find_images(&self.lines),
+            Kind::Quadlet => quadlet::find_images(&self.lines),
+        }
+        .into_iter()
+        .map(Image::Inline)
+        .collect();
+        if self.kind == Kind::Compose {
+            images.extend(helm::find_images(&self.lines).into_iter().map(Image::Split));
+            images.sort_by_key(Image::line);
+        }
+        images
+    }
+
+    /// returns the image of a line
+    fn image(&self, line: usize) -> Option<Image> {
+        self.images().into_iter().find(|image| image.line() == line)
+    }
+
+    /// returns the image reference of a line as it is written
+    pub fn get_raw_image(&self, line: usize) -> Option<String> {
+        match self.image(line)? {
+            Image::Inline(span) => Some(span.get(&self.lines[line]).to_string()),
+            Image::Split(split) => Some(split.get(&self.lines)),
+        }
+    }
+
+    /// returns the image reference of a line with resolved variables
+    pub fn get_image(&self, line: usize) -> Result<String, Error> {
+        let raw = self.get_raw_image(line).ok_or(Error::NoImage)?;
+        let env = self.env.lock().unwrap();
+        env::resolve(&raw, &|name| variable(&env, name)).map_err(Error::Variable)
+    }
+
+    /// returns the name of the service the given line belongs to
+    /// which is the closest key above that is indented less than the line,
+    /// the name of a container in a kubernetes manifest, the name of the build stage in a Dockerfile
+    /// or the container name of a quadlet unit
+    pub fn service_name(&self, line: usize) -> Option<String> {
+        match self.kind {
+            Kind::Dockerfile => return dockerfile::stage_name(self.lines.get(line)?),
+            Kind::Quadlet => {
+                return quadlet::container_name(&self.lines).or_else(|| {
+                    let stem = self.path.file_stem()?;
+                    Some(stem.to_string_lossy().to_string())
+                })
+            }
+            Kind::Compose => (),
+        }
+        if let Some(name) = compose::item_name(&self.lines, line) {
+            return Some(name);
+        }
+        let indent = |l: &str| l.len() - l.trim_start().len();
+
+        //a service written as flow mapping like `web: { image: nginx }`
+        let own = self.lines.get(line)?;
+        if let Some((key, _)) = own.split_once(": {").filter(|_| indent(own) > 0) {
+            return Some(key.trim().to_string());
+        }
+
+        let mut current = indent(own);
+        for l in self.lines[..line].iter().rev() {
+            let trimmed = l.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || indent(l) >= current {
+                continue;
+            }
+            match trimmed.strip_suffix(':') {
+                Some(key) if indent(l) > 0 => return Some(key.to_string()),
+                _ => current = indent(l),
+            }
+        }
+        None
+    }
+
+    /// replaces the image of a line with repo and tag
+    /// an image with variables is changed by setting the variable in the .env file
+    /// and of an image that is split into keys only the keys that change are written
+    /// quadlet units keep the registry as it is written, because podman needs it
+    pub fn change_image(&mut self, line: usize, repo_with_tag: &str) -> Result<(), Error> {
+        let span = match self.image(line).ok_or(Error::NoImage)? {
+            Image::Inline(span) => span,
+            Image::Split(split) => {
+                split.set(&mut self.lines, repo_with_tag)?;
+                self.changed = true;
+                return Ok(());
+            }
+        };
+        let raw = span.get(&self.lines[line]);
+        if self.kind == Kind::Quadlet {
+            let reference = qualify_repository(raw, repo_with_tag);
+            self.lines[line] = span.replace(&self.lines[line], &reference);
+            self.changed = true;
+            return Ok(());
+        }
+        if !env::has_variables(raw) {
+            self.lines[line] = span.replace(&self.lines[line], repo_with_tag);
+            self.changed = true;
+            return Ok(());
+        }
+
+        let mut env = self.env.lock().unwrap();
+        let (name, value) = env::assign(raw, repo_with_tag, &|name| variable(&env, name))
+            .map_err(Error::Variable)?;
+        if std::env::var_os(&name).is_some() {
+            return Err(Error::Variable(env::Error::FromEnvironment(name)));
+        }
+        env.set(&name, &value);
+        self.changed = true;
+        Ok(())
+    }
+
+    /// returns a unified diff between the file on disk and the lines in memory
+    /// changes of the .env file are not included, as it may be shared with other files
+    pub fn diff(&self) -> Result<String, std::io::Error> {
+        let on_disk: Vec<String> = std::fs::read_to_string(&self.path)?
+            .lines()
+            .map(String::from)
+            .collect();
+        let name = self.path.display();
+        let (old, new) = match self.path.is_absolute() {
+            true => (name.to_string(), name.to_string()),
+            false => (format!("a/{}", name), format!("b/{}", name)),
+        };
+        Ok(diff::unified_diff(&old, &new, &on_disk, &self.lines))
+    }
+
+    /// writes the file and its .env file if they were changed
+    /// the .env file holds the changes of all files that share it
+    /// line endings and the permissions of the file are kept
+    pub fn save(&mut self, backup: Backup) -> Result<(), std::io::Error> {
+        if !self.changed {
+            return Ok(());
+        }
+        if self.lines != self.original {
+            let content = self.endings.join(&self.lines);
+            save::write_atomic(&self.path, &content, backup)?;
+            self.fingerprint = Fingerprint::new(&self.path, Some(&content));
+            self.original = self.lines.clone();
+        }
+
+        self.env.lock().unwrap().save(backup)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    /// the paths of the file and its .env file with their state on disk when they were read or saved
+    pub fn fingerprints(&self) -> Vec<(PathBuf, Fingerprint)> {
+        let env = self.env.lock().unwrap();
+        let (env_path, env_fingerprint) = env.fingerprint();
+        vec![
+            (self.path.clone(), self.fingerprint),
+            (env_path.to_path_buf(), env_fingerprint),
+        ]
+    }
+
+    /// checks if the file or its .env file was changed by someone else since it was read or saved
+    pub fn modified_on_disk(&self) -> bool {
+        self.fingerprint.changed(&self.path) || self.env_modified(&self.env.lock().unwrap())
+    }
+
+    /// checks if the .env file was changed on disk and may be read again by this file,
+    /// which is not the case while it holds unsaved changes of another file
+    fn env_modified(&self, env: &EnvFile) -> bool {
+        env.modified_on_disk() && (self.changed || !env.changed())
+    }
+
+    /// reads the file again, unsaved changes are dropped
+    pub fn reload(&mut self) -> Result<(), std::io::Error> {
+        let env = Arc::clone(&self.env);
+        {
+            let mut env = env.lock().unwrap();
+            if self.env_modified(&env) {
+                env.reload();
+            }
+        }
+        *self = Self::open_with_env(&self.path, env)?;
+        Ok(())
+    }
+
+    /// applies the changed lines to the file as it is on disk now without changing this file
+    /// the merge lists the changed lines that were changed on disk as well
+    pub fn merge(&self) -> Result<Merge, std::io::Error> {
+        let content = std::fs::read_to_string(&self.path)?;
+        let theirs: Vec<String> = content.lines().map(String::from).collect();
+        let mapping = diff::line_mapping(&self.original, &theirs);
+
+        //changing images never adds or removes lines, so the lines match the original
+        let mut lines = theirs;
+        let mut conflicts = vec![];
+        for (i, line) in self.lines.iter().enumerate() {
+            if *line == self.original[i] {
+                continue;
+            }
+            match mapping[i] {
+                Some(j) => lines[j] = line.clone(),
+                None => conflicts.push(i),
+            }
+        }
+        Ok(Merge {
+            content,
+            lines,
+            conflicts,
+        })
+    }
+
+    /// takes the merged lines, the file on disk becomes the new base
+    pub fn apply_merge(&mut self, merge: Merge) {
+        self.original = merge.content.lines().map(String::from).collect();
+        self.lines = merge.lines;
+        self.endings = LineEndings::detect(&merge.content);
+        self.fingerprint = Fingerprint::new(&self.path, Some(&merge.content));
+        self.env.lock().unwrap().merge();
+    }
+}
+
+/// the changed lines of a file applied to the file on disk
+pub struct Merge {
+    /// the file on disk
+    content: String,
+    lines: Vec<String>,
+    /// the changed lines that were changed on disk as well, which can't be merged
+    pub conflicts: Vec<usize>,
+}
+
+/// looks up a variable, the environment takes precedence over the .env file
+fn variable(env: &EnvFile, name: &str) -> Option<String> {
+    std::env::var(name).ok().or_else(|| env.get(name))
+}
+
+/// writes the new reference with the registry of the old one, because podman needs it
+/// the repository is kept as it is written when it stays the same
+/// like `docker.io/library/nginx:1.25` and `library/nginx:1.27` to `docker.io/library/nginx:1.27`
+/// or `docker.io/nginx:1.25` and `bitnami/nginx:1.27` to `docker.io/bitnami/nginx:1.27`
+fn qualify_repository(old: &str, new: &str) -> String {
+    let (old_repository, _) = repo::split_tag_from_repo(old).unwrap_or((old, ""));
+    let (new_repository, _) = repo::split_tag_from_repo(new).unwrap_or((new, ""));
+    let old_reference = Reference::parse(old_repository).ok();
+    let new_reference = Reference::parse(new_repository).ok();
+
+    let mut reference = match (old_reference, new_reference) {
+        (Some(o), Some(n)) if o.name() == n.name() => old_repository.to_string(),
+        //the docker hub domain the old repository was written with
+        (Some(o), Some(n)) if o.domain.is_none() && n.domain.is_none() => {
+            match old_repository.split_once('/') {
+                Some((domain, _)) if domain.contains('.') => format!("{}/{}", domain, n.path),
+                _ => new_repository.to_string(),
+            }
+        }
+        _ => new_repository.to_string(),
+    };
+    if let Some((_, tag)) = repo::split_tag_from_repo(new)
+        .ok()
+        .filter(|(_, tag)| !tag.is_empty())
+    {
+        reference.push(':');
+        reference.push_str(tag);
+    }
+    if let (_, Some(digest)) = repo::split_digest_from_repo(new) {
+        reference.push('@');
+        reference.push_str(digest);
+    }
+    reference
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use super::{EnvFile, OpenedFile};
+    use crate::common::save::Backup;
+
+    fn with_lines(path: &Path, lines: Vec<String>) -> OpenedFile {
+        let env = EnvFile::load(path.parent().unwrap());
+        OpenedFile::with_lines(path, lines, Arc::new(Mutex::new(env)))
+    }
+
+    fn file(lines: &[&str]) -> OpenedFile {
+        with_lines(
+            Path::new("/nonexistent/docker-compose.yml"),
+            lines.iter().map(|l| l.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_service_name() {
+        let file = file(&[
+            "services:",
+            "  web:",
+            "    image: nginx:1.25",
+            "    ports:",
+            "      - 80:80",
+            "  # the database",
+            "  db:",
+            "    environment:",
+            "      POSTGRES_USER: user",
+            "    image: postgres:15.4-bookworm",
+            "  cache: { image: redis:7 }",
+            "image: broken",
+        ]);
+
+        assert_eq!(file.image_lines(), vec![2, 9, 10]);
+        assert_eq!(file.service_name(2), Some(String::from("web")));
+        assert_eq!(file.service_name(9), Some(String::from("db")));
+        assert_eq!(file.service_name(10), Some(String::from("cache")));
+        assert_eq!(file.service_name(11), None);
+    }
+
+    #[test]
+    fn test_change_image() {
+        let input: Vec<(&str, &str)> = vec![
+            ("  image: nginx", "  image: nginx:1.27"),
+            ("  image: nginx # comment", "  image: nginx:1.27 # comment"),
+            (
+                "  image: nginx:1.25 # pinned until migration",
+                "  image: nginx:1.27 # pinned until migration",
+            ),
+            ("  image: \"nginx:1.25\"", "  image: \"nginx:1.27\""),
+            (
+                "  image: 'nginx:1.25' # quoted",
+                "  image: 'nginx:1.27' # quoted",
+            ),
+            (
+                "  web: { image: nginx:1.25, ports: [80] }",
+                "  web: { image: nginx:1.27, ports: [80] }",
+            ),
+        ];
+
+        for i in input {
+            let mut file = file(&[i.0]);
+            file.change_image(0, "nginx:1.27").unwrap();
+            assert_eq!(file.lines[0], i.1);
+            assert!(file.changed());
+        }
+        assert!(file(&["  ports: [80]"]).change_image(0, "nginx").is_err());
+    }
+
+    #[test]
+    fn test_dockerfile() {
+        let mut file = with_lines(
+            Path::new("/nonexistent/Dockerfile"),
+            vec![
+                String::from("FROM rust:1.77 AS builder"),
+                String::from("FROM builder"),
+            ],
+        );
+        assert_eq!(file.image_lines(), vec![0]);
+        assert_eq!(file.service_name(0), Some(String::from("builder")));
+        file.change_image(0, "rust:1.78").unwrap();
+        assert_eq!(file.lines[0], "FROM rust:1.78 AS builder");
+        assert!(file.change_image(1, "rust:1.78").is_err());
+    }
+
+    #[test]
+    fn test_quadlet() {
+        let mut file = with_lines(
+            Path::new("/nonexistent/web.container"),
+            vec![
+                String::from("[Container]"),
+                String::from("Image=docker.io/library/nginx:1.25"),
+            ],
+        );
+        assert_eq!(file.image_lines(), vec![1]);
+        assert_eq!(file.service_name(1), Some(String::from("web")));
+        file.change_image(1, "library/nginx:1.27").unwrap();
+        assert_eq!(file.lines[1], "Image=docker.io/library/nginx:1.27");
+        file.change_image(1, "library/nginx:1.27@sha256:0123abcd")
+            .unwrap();
+        assert_eq!(
+            file.lines[1],
+            "Image=docker.io/library/nginx:1.27@sha256:0123abcd"
+        );
+
+        //another repository of docker hub is qualified the same way
+        file.change_image(1, "bitnami/nginx:1.27").unwrap();
+        assert_eq!(file.lines[1], "Image=docker.io/bitnami/nginx:1.27");
+        file.change_image(1, "nginx:1.27").unwrap();
+        assert_eq!(file.lines[1], "Image=docker.io/library/nginx:1.27");
+        file.change_image(1, "ghcr.io/nginx/nginx:1.27").unwrap();
+        assert_eq!(file.lines[1], "Image=ghcr.io/nginx/nginx:1.27");
+    }
+
+    #[test]
+    fn test_save_keeps_line_endings() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("compose.yaml");
+        std::fs::write(&path, "services:\r\n  web:\r\n    image: nginx:1.25").unwrap();
+
+        let mut file = OpenedFile::open(&path).unwrap();
+        file.change_image(2, "nginx:1.27").unwrap();
+        file.save(Backup::None).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "services:\r\n  web:\r\n    image: nginx:1.27");
+    }
+
+    #[test]
+    fn test_split_image() {
+        let mut file = file(&[
+            "image:",
+            "  repository: bitnami/nginx",
+            "  tag: \"1.25.3\" # pinned",
+        ]);
+        assert_eq!(file.image_lines(), vec![1]);
+        assert_eq!(
+            file.get_raw_image(1),
+            Some(String::from("bitnami/nginx:1.25.3"))
+        );
+        file.change_image(1, "bitnami/nginx:1.27.0").unwrap();
+        assert_eq!(file.lines[1], "  repository: bitnami/nginx");
+        assert_eq!(file.lines[2], "  tag: \"1.27.0\" # pinned");
+
+        //a digest needs a key to be written to
+        let pinned = "bitnami/nginx:1.27.0@sha256:0123abcd";
+        assert!(matches!(
+            file.change_image(1, pinned),
+            Err(super::Error::NoDigestKey)
+        ));
+        assert_eq!(file.lines[2], "  tag: \"1.27.0\" # pinned");
+    }
+
+    #[test]
+    fn test_split_image_repository_and_digest() {
+        let mut file = file(&[
+            "image:",
+            "  registry: docker.io",
+            "  repository: bitnami/nginx",
+            "  tag: 1.25.3",
+            "  digest: \"\"",
+        ]);
+        file.change_image(2, "ghcr.io/nginx/nginx:1.27.0@sha256:0123abcd")
+            .unwrap();
+        assert_eq!(
+            file.lines[1..],
+            [
+                "  registry: ghcr.io",
+                "  repository: nginx/nginx",
+                "  tag: 1.27.0",
+                "  digest: \"sha256:0123abcd\"",
+            ]
+        );
+        assert_eq!(
+            file.get_raw_image(2),
+            Some(String::from("ghcr.io/nginx/nginx:1.27.0@sha256:0123abcd"))
+        );
+
+        //a tag without digest empties the digest
+        file.change_image(2, "ghcr.io/nginx/nginx:1.27.1").unwrap();
+        assert_eq!(file.lines[4], "  digest: \"\"");
+
+        let mut file = self::file(&["image:", "  repository: nginx", "  tag: 1.25", "  digest:"]);
+        file.change_image(1, "bitnami/nginx:1.27@sha256:0123abcd")
+            .unwrap();
+        assert_eq!(
+            file.lines[1..],
+            [
+                "  repository: bitnami/nginx",
+                "  tag: 1.27",
+                "  digest: sha256:0123abcd"
+            ]
+        );
+    }
+}
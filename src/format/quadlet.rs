@@ -0,0 +1,93 @@
+use regex::Regex;
+
+use super::ImageSpan;
+
+lazy_static::lazy_static! {
+    static ref SECTION: Regex = Regex::new(r"^[ \t]*\[([^\]]+)\][ \t]*$").unwrap();
+    static ref KEY: Regex = Regex::new(r"^[ \t]*([A-Za-z]+)[ \t]*=[ \t]*(.*?)[ \t]*$").unwrap();
+}
+
+/// the sections of quadlet units that name an image
+const SECTIONS: [&str; 2] = ["Container", "Image"];
+
+/// checks if a file name belongs to a podman quadlet unit with an image
+pub fn is_quadlet(name: &str) -> bool {
+    name.ends_with(".container") || name.ends_with(".image")
+}
+
+/// returns the value of every key in the image sections of a unit as line and span
+fn values<'a>(lines: &'a [String]) -> impl Iterator<Item = (&'a str, ImageSpan)> + 'a {
+    let mut section = String::new();
+    lines.iter().enumerate().filter_map(move |(i, line)| {
+        if let Some(caps) = SECTION.captures(line) {
+            section = caps[1].to_string();
+            return None;
+        }
+        if !SECTIONS.contains(&section.as_str()) {
+            return None;
+        }
+        let caps = KEY.captures(line)?;
+        let key = caps.get(1)?.as_str();
+        let value = caps.get(2)?;
+        Some((
+            key,
+            ImageSpan {
+                line: i,
+                start: value.start(),
+                end: value.end(),
+                quote: None,
+            },
+        ))
+    })
+}
+
+/// finds the `Image=` keys of a quadlet unit
+/// images with specifiers like `%i` or environment variables are skipped
+pub fn find_images(lines: &[String]) -> Vec<ImageSpan> {
+    values(lines)
+        .filter(|(key, span)| {
+            let value = span.get(&lines[span.line]);
+            *key == "Image" && !value.is_empty() && !value.contains(['%', '$', ' '])
+        })
+        .map(|(_, span)| span)
+        .collect()
+}
+
+/// returns the value of `ContainerName=`
+pub fn container_name(lines: &[String]) -> Option<String> {
+    values(lines)
+        .find(|(key, _)| *key == "ContainerName")
+        .map(|(_, span)| span.get(&lines[span.line]).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_find_images() {
+        let lines = lines(
+            "[Unit]
+Description=Image=not-an-image
+
+[Container]
+# Image=commented
+ContainerName=web
+Image=docker.io/library/nginx:1.25  
+PublishPort=8080:80
+Image=quay.io/%i:latest
+
+[Service]
+Image=ignored",
+        );
+
+        let images: Vec<(usize, &str)> = super::find_images(&lines)
+            .iter()
+            .map(|span| (span.line, span.get(&lines[span.line])))
+            .collect();
+        assert_eq!(images, vec![(6, "docker.io/library/nginx:1.25")]);
+        assert_eq!(super::container_name(&lines), Some(String::from("web")));
+    }
+}
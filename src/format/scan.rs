@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use super::opened_file::{Kind, OpenedFile};
+
+/// finds all files below a directory that contain images, ordered by path
+/// `.git` and everything that is ignored by a `.gitignore` is skipped
+pub fn find_files(dir: &Path) -> Vec<PathBuf> {
+    let walker = ignore::WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut files: Vec<PathBuf> = walker
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| Kind::detect(path).is_some())
+        .filter(|path| match OpenedFile::open(path) {
+            Ok(file) => !file.image_lines().is_empty(),
+            Err(_) => false,
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_find_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let files = [
+            ("compose.yaml", "services:\n  web:\n    image: nginx:1.25\n"),
+            ("app/Dockerfile", "FROM rust:1.77 AS builder\n"),
+            ("app/README.md", "image: not-a-manifest\n"),
+            ("deploy/config.yaml", "replicas: 3\n"),
+            (
+                "deploy/web.container",
+                "[Container]\nImage=docker.io/library/nginx:1.25\n",
+            ),
+            (
+                "build/compose.yaml",
+                "services:\n  web:\n    image: ignored:1\n",
+            ),
+            (
+                ".git/compose.yaml",
+                "services:\n  web:\n    image: ignored:1\n",
+            ),
+            (".gitignore", "build/\n"),
+        ];
+        for (name, content) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let found = super::find_files(dir);
+        assert_eq!(
+            found,
+            ["app/Dockerfile", "compose.yaml", "deploy/web.container"]
+                .iter()
+                .map(|name| dir.join(name))
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+}
@@ -1,9 +1,17 @@
 use std::path::PathBuf;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use anyhow::Result;
 
+use cli::report::Format;
+use common::save::Backup;
+use regex::Regex;
+use repository::Upgrade;
+
+mod cli;
 mod common;
+mod config;
 mod error;
+mod format;
 mod repo;
 mod repository;
 mod ui;
@@ -12,17 +20,138 @@ mod widget;
 /// helps you searching or updating tags of your used docker images
 #[derive(Parser, Debug)]
 pub struct Args {
-    /// A custom path to a docker-compose file
-    #[arg(short, long)]
-    file: Option<PathBuf>,
+    /// A custom path to a docker-compose file, can be repeated to stack override files
+    #[arg(short, long, global = true)]
+    file: Vec<PathBuf>,
 
     /// Give a Repository identifier, e.g. library/nginx
     #[arg(short, long)]
     repo: Option<String>,
+
+    /// Keep a copy of every file before it is overwritten
+    #[arg(long, value_enum, global = true, default_value_t = Backup::None)]
+    backup: Backup,
+
+    /// Location of the docker client config files, defaults to $DOCKER_CONFIG or ~/.docker
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// commands that run without the tui
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare the tags of the compose file with the newest tags of the same variant,
+    /// exits with 1 when an image is outdated and with 2 when an image could not be checked
+    Check {
+        /// The smallest upgrade that counts as outdated
+        #[arg(long, value_enum, default_value_t = Upgrade::Patch)]
+        threshold: Upgrade,
+
+        /// How the result is printed
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Update the images of the compose file to the newest tag of the same variant
+    /// that is allowed by the policy and save the file
+    Update {
+        /// The biggest upgrade that is applied
+        #[arg(long, value_enum)]
+        policy: Upgrade,
+
+        /// Only update the image of this service
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Print a unified diff of the changes instead of saving them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// How the result is printed
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Check the images of every compose file, Dockerfile and manifest below a directory,
+    /// `.git` and files ignored by `.gitignore` are skipped
+    Scan {
+        /// The directory to scan
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+
+        /// Browse the found files in the tui instead of printing a report
+        #[arg(long)]
+        tui: bool,
+
+        /// The smallest upgrade that counts as outdated
+        #[arg(long, value_enum, default_value_t = Upgrade::Patch)]
+        threshold: Upgrade,
+
+        /// How the result is printed
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Print the tags of a repository with their last update and digest
+    Tags {
+        /// A Repository identifier, e.g. library/nginx or ghcr.io/owner/image
+        repo: String,
+
+        /// The maximum count of printed tags
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Only print tags that match this regular expression
+        #[arg(long, value_parser = Regex::new)]
+        filter: Option<Regex>,
+
+        /// Only print tags that have an image for this architecture, e.g. arm64 or arm/v7
+        #[arg(long)]
+        arch: Option<String>,
+
+        /// How the tags are printed
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
 }
 
 fn main() -> Result<()> {
     //parse parameter
     let args = Args::parse();
-    ui::create_ui(&args)
+    let config = config::Config::load()?;
+    repository::set_config_dir(args.config.clone());
+    repository::set_overrides(config.backends);
+    match &args.command {
+        None => ui::create_ui(&args),
+        Some(Command::Check { threshold, format }) => {
+            let code = cli::check::run(&args, *threshold, *format)?;
+            std::process::exit(code)
+        }
+        Some(Command::Update {
+            policy,
+            service,
+            format,
+            dry_run,
+        }) => {
+            let code = cli::update::run(&args, *policy, service, *format, *dry_run)?;
+            std::process::exit(code)
+        }
+        Some(Command::Scan { dir, tui: true, .. }) => ui::create_scan_ui(&args, dir),
+        Some(Command::Scan {
+            dir,
+            threshold,
+            format,
+            ..
+        }) => {
+            let code = cli::check::scan(dir, *threshold, *format)?;
+            std::process::exit(code)
+        }
+        Some(Command::Tags {
+            repo,
+            limit,
+            filter,
+            arch,
+            format,
+        }) => Ok(cli::tags::run(repo, *limit, filter, arch, *format)?),
+    }
 }
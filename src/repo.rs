@@ -2,152 +2,249 @@ use regex::Regex;
 
 use crate::error::Error;
 
-#[derive(Debug, PartialEq)]
-pub enum Repo {
-    WithServer(String, String, String),
-    WithOrga(String, String),
-    Project(String),
+/// the aliases under which docker hub is reachable
+const DOCKER_HUB_DOMAINS: [&str; 3] = ["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// a parsed image reference in the form of `[domain[:port]/]path[:tag][@digest]`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    /// registry with optional port, None for docker hub
+    pub domain: Option<String>,
+    /// repository path, official images of docker hub are prefixed with library/
+    pub path: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
 }
 
-/// check if yaml line matches and returns the split of repo string and rest
-/// the first &str is the image tag
-/// it will be used to not change the identation
-/// the second &str will the the identifier for the image
-pub fn match_yaml_image(input: &str) -> Result<(&str, &str), Error> {
-    lazy_static::lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"^( +image *: *)([a-z0-9\-\./:]+)").unwrap();
+impl Reference {
+    /// parses a reference following the grammar of the distribution project
+    /// docker hub aliases are normalized to a missing domain
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        lazy_static::lazy_static! {
+            static ref DOMAIN: Regex = Regex::new(
+                r"^[a-zA-Z0-9]([a-zA-Z0-9-]*[a-zA-Z0-9])?(\.[a-zA-Z0-9]([a-zA-Z0-9-]*[a-zA-Z0-9])?)*(:[0-9]+)?$"
+            ).unwrap();
+            static ref PATH_COMPONENT: Regex =
+                Regex::new(r"^[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*$").unwrap();
+            static ref TAG: Regex = Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.-]{0,127}$").unwrap();
+            static ref DIGEST: Regex =
+                Regex::new(r"^[a-z0-9]+([+._-][a-z0-9]+)*:[a-zA-Z0-9=_-]+$").unwrap();
+        }
+
+        let (input, digest) = split_digest_from_repo(input.trim());
+        let (name, tag) = split_tag_from_repo(input)?;
+        let tag = match input.len() > name.len() {
+            true => Some(tag),
+            false => None,
+        };
+        if tag.is_some_and(|t| !TAG.is_match(t)) || digest.is_some_and(|d| !DIGEST.is_match(d)) {
+            return Err(Error::MisformedInput);
+        }
+
+        //the first component is a domain if it looks like a host
+        let mut domain = None;
+        let mut path = name;
+        if let Some((first, rest)) = name.split_once('/') {
+            if first.contains('.')
+                || first.contains(':')
+                || first == "localhost"
+                || first.chars().any(|c| c.is_ascii_uppercase())
+            {
+                if !DOMAIN.is_match(first) {
+                    return Err(Error::MisformedInput);
+                }
+                domain = Some(first);
+                path = rest;
+            }
+        }
+        if !path.split('/').all(|c| PATH_COMPONENT.is_match(c)) {
+            return Err(Error::MisformedInput);
+        }
+
+        let domain = domain.filter(|d| !DOCKER_HUB_DOMAINS.contains(d));
+        let path = match (domain, path.contains('/')) {
+            (None, false) => format!("library/{}", path),
+            _ => path.to_string(),
+        };
+
+        Ok(Self {
+            domain: domain.map(String::from),
+            path,
+            tag: tag.map(String::from),
+            digest: digest.map(String::from),
+        })
+    }
+
+    /// the repository name without tag and digest, prefixed with the domain if it is not docker hub
+    pub fn name(&self) -> String {
+        match &self.domain {
+            None => self.path.clone(),
+            Some(domain) => format!("{}/{}", domain, self.path),
+        }
     }
-    let caps = match REGEX.captures(input) {
-        Some(caps) => caps,
-        None => return Err(Error::NoTagFound),
-    };
+}
 
-    Ok((caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str()))
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
 }
 
 /// takes the identifier and splits off the tag it exists
+/// a pinned digest is ignored
 pub fn split_tag_from_repo(input: &str) -> Result<(&str, &str), Error> {
-    lazy_static::lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"^([a-z0-9\./[^:]]*):?([a-z0-9._\-]*)").unwrap();
+    let (input, _) = split_digest_from_repo(input);
+    if input.is_empty() {
+        return Err(Error::MisformedInput);
     }
-    let (front, back) = match REGEX.captures(input) {
-        None => return Err(Error::MisformedInput),
-        Some(caps) => {
-            let front = match caps.get(1) {
-                None => return Err(Error::MisformedInput),
-                Some(cap) => cap.as_str(),
-            };
-            let back = match caps.get(2) {
-                None => "",
-                Some(cap) => cap.as_str(),
-            };
-            (front, back)
-        }
-    };
 
-    Ok((front, back))
+    //a colon before the last slash separates a port
+    let last_component = input.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match input[last_component..].find(':') {
+        None => Ok((input, "")),
+        Some(i) => {
+            let colon = last_component + i;
+            Ok((&input[..colon], &input[colon + 1..]))
+        }
+    }
 }
 
-/// takes an identifier and changes it to a Repo enum
-pub fn split_repo_without_tag(repo: &str) -> Result<Repo, Error> {
-    let repo = repo.trim();
-    let split_repo: Vec<&str> = repo.split('/').collect();
-    match split_repo.len() {
-        1 => {
-            let regex = regex::Regex::new(r"[a-z0-9]+").unwrap();
-            match regex.is_match(repo) {
-                false => Err(Error::MisformedInput),
-                true => Ok(Repo::Project(split_repo[0].into())),
-            }
-        }
-        2 => {
-            let regex = regex::Regex::new(r"[a-z0-9]+/[a-z0-9]+").unwrap();
-            match regex.is_match(repo) {
-                false => Err(Error::MisformedInput),
-                true => Ok(Repo::WithOrga(split_repo[0].into(), split_repo[1].into())),
-            }
-        }
-        3 => {
-            let regex = regex::Regex::new(r"[a-z0-9\.]+/[a-z0-9]+/[a-z0-9]+").unwrap();
-            match regex.is_match(repo) {
-                false => Err(Error::MisformedInput),
-                true => Ok(Repo::WithServer(
-                    split_repo[0].into(),
-                    split_repo[1].into(),
-                    split_repo[2].into(),
-                )),
-            }
-        }
-        _ => Err(Error::MisformedInput),
+/// takes the identifier and splits off a digest like `@sha256:...` if it exists
+pub fn split_digest_from_repo(input: &str) -> (&str, Option<&str>) {
+    match input.split_once('@') {
+        None => (input, None),
+        Some((front, digest)) => (front, Some(digest)),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::repo::{Error, Repo};
+    use crate::repo::{Error, Reference};
+
+    fn reference(domain: Option<&str>, path: &str, tag: Option<&str>) -> Reference {
+        Reference {
+            domain: domain.map(String::from),
+            path: String::from(path),
+            tag: tag.map(String::from),
+            digest: None,
+        }
+    }
 
     #[test]
-    fn test_split_repo_without_tag_error() {
-        let input: Vec<&str> = vec!["", "NGINX"];
+    fn test_parse_reference_error() {
+        let input: Vec<&str> = vec![
+            "",
+            "NGINX",
+            "library/NGINX",
+            "nginx:",
+            "nginx:-tag",
+            "nginx@sha256",
+            "-registry.com/app",
+            "registry.com:port/app",
+            "team//app",
+        ];
         for i in input {
-            assert!(super::split_repo_without_tag(i).is_err());
+            assert!(Reference::parse(i).is_err(), "{}", i);
         }
     }
 
     #[test]
-    fn test_split_repo_without_tag() -> Result<(), Error> {
-        let input: Vec<(&str, Repo)> = vec![
-            ("nginx", Repo::Project("nginx".into())),
-            (
-                "library/nginx",
-                Repo::WithOrga("library".into(), "nginx".into()),
-            ),
+    fn test_parse_reference() -> Result<(), Error> {
+        let input: Vec<(&str, Reference)> = vec![
+            ("nginx", reference(None, "library/nginx", None)),
+            ("library/nginx", reference(None, "library/nginx", None)),
             (
                 "ghcr.io/library/nginx",
-                Repo::WithServer("ghcr.io".into(), "library".into(), "nginx".into()),
+                reference(Some("ghcr.io"), "library/nginx", None),
             ),
             (
                 "te-st/test-hypen",
-                Repo::WithOrga("te-st".into(), "test-hypen".into()),
+                reference(None, "te-st/test-hypen", None),
+            ),
+            ("test/test.dot", reference(None, "test/test.dot", None)),
+            (
+                "docker.io/library/nginx",
+                reference(None, "library/nginx", None),
+            ),
+            (
+                "index.docker.io/nginx",
+                reference(None, "library/nginx", None),
+            ),
+            (
+                "registry-1.docker.io/bitnami/redis:7.2",
+                reference(None, "bitnami/redis", Some("7.2")),
             ),
             (
-                "test/test.dot",
-                Repo::WithOrga("test".into(), "test.dot".into()),
+                "localhost:5000/team/app:1.0",
+                reference(Some("localhost:5000"), "team/app", Some("1.0")),
+            ),
+            ("localhost/app", reference(Some("localhost"), "app", None)),
+            (
+                "registry.gitlab.com/group/sub/project/image:latest",
+                reference(
+                    Some("registry.gitlab.com"),
+                    "group/sub/project/image",
+                    Some("latest"),
+                ),
+            ),
+            (
+                "192.168.1.10:5000/a__b/c",
+                reference(Some("192.168.1.10:5000"), "a__b/c", None),
+            ),
+            (
+                "nginx:1.25-alpine@sha256:0123abcd",
+                Reference {
+                    digest: Some("sha256:0123abcd".into()),
+                    ..reference(None, "library/nginx", Some("1.25-alpine"))
+                },
             ),
         ];
 
         for i in input {
-            assert_eq!(super::split_repo_without_tag(i.0)?, i.1);
+            assert_eq!(Reference::parse(i.0)?, i.1, "{}", i.0);
         }
         Ok(())
     }
 
     #[test]
-    fn test_match_yaml_image_error() {
-        let input: Vec<&str> = vec!["", "version: '2'", "image: ", "  image: "];
+    fn test_reference_display() -> Result<(), Error> {
+        let input: Vec<(&str, &str)> = vec![
+            ("nginx", "library/nginx"),
+            ("docker.io/library/nginx:1.25", "library/nginx:1.25"),
+            (
+                "localhost:5000/app:1@sha256:0123abcd",
+                "localhost:5000/app:1@sha256:0123abcd",
+            ),
+        ];
+
         for i in input {
-            assert!(super::match_yaml_image(i).is_err());
+            assert_eq!(format!("{}", Reference::parse(i.0)?), i.1);
         }
+        Ok(())
     }
 
     #[test]
-    fn test_match_yaml_image() -> Result<(), Error> {
-        let input: Vec<(&str, (&str, &str))> = vec![
-            ("  image: nginx", ("  image: ", "nginx")),
-            ("  image: library/nginx", ("  image: ", "library/nginx")),
+    fn test_split_digest_from_repo() {
+        let input: Vec<(&str, (&str, Option<&str>))> = vec![
+            ("nginx", ("nginx", None)),
+            ("nginx:1.25", ("nginx:1.25", None)),
             (
-                "  image: ghcr.io/library/nginx",
-                ("  image: ", "ghcr.io/library/nginx"),
+                "nginx:1.25@sha256:0123abcd",
+                ("nginx:1.25", Some("sha256:0123abcd")),
             ),
-            ("  image: nginx # comment", ("  image: ", "nginx")),
-            ("  image: test-hyphen", ("  image: ", "test-hyphen")),
-            ("  image: test.dot", ("  image: ", "test.dot")),
+            ("nginx@sha256:0123abcd", ("nginx", Some("sha256:0123abcd"))),
         ];
 
         for i in input {
-            assert_eq!(super::match_yaml_image(i.0)?, i.1);
+            assert_eq!(super::split_digest_from_repo(i.0), i.1);
         }
-        Ok(())
     }
 
     #[test]
@@ -165,6 +262,10 @@ mod tests {
                 "woodpeckerci/woodpecker-server",
                 ("woodpeckerci/woodpecker-server", ""),
             ),
+            ("nginx:1.25@sha256:0123abcd", ("nginx", "1.25")),
+            ("nginx@sha256:0123abcd", ("nginx", "")),
+            ("localhost:5000/app", ("localhost:5000/app", "")),
+            ("localhost:5000/app:1.0", ("localhost:5000/app", "1.0")),
         ];
 
         for i in input {
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use base64::Engine;
+use reqwest::header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Response, StatusCode, Url};
+use serde::Deserialize;
+
+use super::credentials::{self, Credentials};
+use crate::error::Error;
+
+lazy_static::lazy_static! {
+    static ref CLIENT: reqwest::Client = reqwest::Client::new();
+    /// tokens that were handed out by a registry, the key is host and scope
+    static ref TOKENS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// a parsed `WWW-Authenticate: Bearer ...` challenge
+#[derive(Debug, PartialEq)]
+struct Challenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+/// sends a GET request to a registry
+/// when the registry answers with a Bearer challenge a token is fetched and the request is retried
+/// credentials from the docker config are used when there are some for the registry
+pub async fn get(url: &str) -> Result<Response, Error> {
+    get_accepting(url, None).await
+}
+
+/// same as get, but asks for specific media types with an Accept header
+pub async fn get_accepting(url: &str, accept: Option<&str>) -> Result<Response, Error> {
+    let parsed = Url::parse(url).map_err(|e| Error::Converting(format!("{}", e)))?;
+    let host = parsed.host_str().unwrap_or_default().to_string();
+    let registry = match parsed.port() {
+        None => host.clone(),
+        Some(port) => format!("{}:{}", host, port),
+    };
+    let credentials = credentials::lookup(&registry).await;
+
+    //docker hub does not use challenges but a login
+    //a rejected login like an expired token falls back to an anonymous request,
+    //the login error is only returned if that is refused as well
+    if url.starts_with(super::dockerhub::API_URL) {
+        let Some(credentials) = &credentials else {
+            return send(url, accept, None).await;
+        };
+        return match hub_login(credentials).await {
+            Ok(token) => send(url, accept, Some(&format!("Bearer {}", token))).await,
+            Err(e) => {
+                let response = send(url, accept, None).await?;
+                match response.status() {
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(e),
+                    _ => Ok(response),
+                }
+            }
+        };
+    }
+
+    //reuse a token of an earlier handshake
+    let cached = guess_scope(url)
+        .and_then(|scope| cached_token(&host, &scope))
+        .map(|token| format!("Bearer {}", token));
+    let response = send(url, accept, cached.as_deref()).await?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    let header = response
+        .headers()
+        .get(WWW_AUTHENTICATE)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if header.to_lowercase().starts_with("basic") {
+        return match &credentials {
+            None => Ok(response),
+            Some(credentials) => send(url, accept, Some(&basic(credentials))).await,
+        };
+    }
+    let challenge = match parse_challenge(&header) {
+        None => return Ok(response),
+        Some(challenge) => challenge,
+    };
+
+    let token = fetch_token(&challenge, credentials.as_ref()).await?;
+    let scope = challenge.scope.unwrap_or_default();
+    TOKENS
+        .lock()
+        .unwrap()
+        .insert(cache_key(&host, &scope), token.clone());
+
+    send(url, accept, Some(&format!("Bearer {}", token))).await
+}
+
+async fn send(
+    url: &str,
+    accept: Option<&str>,
+    authorization: Option<&str>,
+) -> Result<Response, Error> {
+    let mut request = CLIENT.get(url);
+    if let Some(accept) = accept {
+        request = request.header(ACCEPT, accept);
+    }
+    if let Some(authorization) = authorization {
+        request = request.header(AUTHORIZATION, authorization);
+    }
+    Ok(request.send().await?)
+}
+
+fn basic(credentials: &Credentials) -> String {
+    let pair = format!("{}:{}", credentials.username, credentials.secret);
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(pair)
+    )
+}
+
+/// requests a token from the realm of the challenge, anonymous when there are no credentials
+async fn fetch_token(
+    challenge: &Challenge,
+    credentials: Option<&Credentials>,
+) -> Result<String, Error> {
+    let mut query = vec![];
+    if let Some(service) = &challenge.service {
+        query.push(("service", service));
+    }
+    if let Some(scope) = &challenge.scope {
+        query.push(("scope", scope));
+    }
+
+    let mut request = CLIENT.get(&challenge.realm).query(&query);
+    if let Some(credentials) = credentials {
+        request = request.header(AUTHORIZATION, basic(credentials));
+    }
+    let response = request
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+
+    match response.token.or(response.access_token) {
+        Some(token) => Ok(token),
+        None => Err(Error::Converting(String::from(
+            "registry did not hand out a token",
+        ))),
+    }
+}
+
+/// logs into docker hub and returns the token that authorizes further requests
+async fn hub_login(credentials: &Credentials) -> Result<String, Error> {
+    let key = cache_key(&credentials.username, "login");
+    if let Some(token) = TOKENS.lock().unwrap().get(&key) {
+        return Ok(token.clone());
+    }
+
+    let mut body = HashMap::new();
+    body.insert("username", &credentials.username);
+    body.insert("password", &credentials.secret);
+    let response = CLIENT
+        .post(format!("{}v2/users/login", super::dockerhub::API_URL))
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+
+    match response.token {
+        None => Err(Error::Converting(String::from(
+            "docker hub did not hand out a token",
+        ))),
+        Some(token) => {
+            TOKENS.lock().unwrap().insert(key, token.clone());
+            Ok(token)
+        }
+    }
+}
+
+fn cache_key(host: &str, scope: &str) -> String {
+    format!("{} {}", host, scope)
+}
+
+fn cached_token(host: &str, scope: &str) -> Option<String> {
+    TOKENS.lock().unwrap().get(&cache_key(host, scope)).cloned()
+}
+
+/// derives the scope a registry will ask for from an url like /v2/<name>/tags/list
+fn guess_scope(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let path = url.path().strip_prefix("/v2/")?;
+    let name = ["/tags/", "/manifests/", "/blobs/"]
+        .iter()
+        .find_map(|sep| path.split_once(sep).map(|(name, _)| name))?;
+    Some(format!("repository:{}:pull", name))
+}
+
+/// parses a header in the form of `Bearer realm="...",service="...",scope="..."`
+fn parse_challenge(header: &str) -> Option<Challenge> {
+    let (scheme, params) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    //split on commas that are not quoted
+    let mut pairs = HashMap::new();
+    let mut in_quotes = false;
+    let mut current = String::new();
+    for c in params.chars().chain(std::iter::once(',')) {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                if let Some((key, value)) = current.split_once('=') {
+                    pairs.insert(key.trim().to_lowercase(), value.trim().to_string());
+                }
+                current.clear();
+            }
+            c => current.push(c),
+        }
+    }
+
+    Some(Challenge {
+        realm: pairs.remove("realm")?,
+        service: pairs.remove("service"),
+        scope: pairs.remove("scope"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::Challenge;
+    use crate::repository::mock_registry::{MockRegistry, Response};
+
+    #[test]
+    fn test_parse_challenge() {
+        assert_eq!(
+            super::parse_challenge(
+                r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/nginx:pull,push""#
+            ),
+            Some(Challenge {
+                realm: "https://auth.docker.io/token".into(),
+                service: Some("registry.docker.io".into()),
+                scope: Some("repository:library/nginx:pull,push".into()),
+            })
+        );
+        assert_eq!(
+            super::parse_challenge(r#"Bearer realm="https://ghcr.io/token""#),
+            Some(Challenge {
+                realm: "https://ghcr.io/token".into(),
+                service: None,
+                scope: None,
+            })
+        );
+        assert_eq!(super::parse_challenge(r#"Basic realm="registry""#), None);
+    }
+
+    #[test]
+    fn test_guess_scope() {
+        assert_eq!(
+            super::guess_scope("https://ghcr.io/v2/org/app/tags/list?n=10"),
+            Some("repository:org/app:pull".into())
+        );
+        assert_eq!(
+            super::guess_scope("https://ghcr.io/v2/org/app/manifests/latest"),
+            Some("repository:org/app:pull".into())
+        );
+        assert_eq!(super::guess_scope("https://ghcr.io/token"), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_handshake() {
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        let counter = token_requests.clone();
+        let registry = Arc::new(std::sync::OnceLock::<String>::new());
+        let realm = registry.clone();
+        let mock = MockRegistry::start(move |request| {
+            if request.path.starts_with("/token?") {
+                assert!(request.path.contains("scope=repository%3Aorg%2Fapp%3Apull"));
+                counter.fetch_add(1, Ordering::SeqCst);
+                return Response::ok(r#"{"token":"secret"}"#);
+            }
+            match request.header("Authorization") {
+                Some("Bearer secret") => Response::ok(r#"{"name":"org/app","tags":["1.0"]}"#),
+                _ => Response::status(401).with_header(
+                    "WWW-Authenticate",
+                    &format!(
+                        r#"Bearer realm="http://{}/token",service="mock",scope="repository:org/app:pull""#,
+                        realm.get().unwrap()
+                    ),
+                ),
+            }
+        });
+        registry.set(mock.host()).unwrap();
+
+        let repo = format!("{}/org/app", mock.host());
+        for _ in 0..2 {
+            let tags = crate::repository::Repo::new(&repo).await.unwrap();
+            assert_eq!(tags.get_tags()[0].get_name(), "1.0");
+        }
+        assert_eq!(token_requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::{distribution, dockerhub, Tag};
+use crate::error::Error;
+
+lazy_static::lazy_static! {
+    /// backends that were chosen in the config file, the key is the registry host
+    static ref OVERRIDES: Mutex<HashMap<String, BackendKind>> = Mutex::new(HashMap::new());
+}
+
+/// one page of tags and the url of the following page
+pub struct Page {
+    pub tags: Vec<Tag>,
+    pub next_page: Option<String>,
+}
+
+/// the api of a registry that is able to list tags
+#[async_trait]
+pub trait RegistryBackend: Send + Sync {
+    /// fetches the first page of tags of a repository
+    async fn list_tags(&self, repo: &str) -> Result<Page, Error>;
+
+    /// fetches the page behind an url that was returned with a previous page
+    async fn next_page(&self, url: &str) -> Result<Page, Error>;
+
+    /// fetches a single tag with its digest and platform details
+    async fn fetch_tag(&self, repo: &str, tag: &str) -> Result<Tag, Error>;
+}
+
+/// the implementations of RegistryBackend that can be chosen for a registry
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    DockerHub,
+    Distribution,
+}
+
+/// sets which backend should be used for which registry host
+pub fn set_overrides(overrides: HashMap<String, BackendKind>) {
+    *OVERRIDES.lock().unwrap() = overrides;
+}
+
+/// chooses the kind of backend for a registry host, None stands for docker hub
+fn select(registry: Option<&str>, overrides: &HashMap<String, BackendKind>) -> BackendKind {
+    let registry = registry.unwrap_or("docker.io");
+    match overrides.get(registry) {
+        Some(kind) => *kind,
+        None if registry == "docker.io" => BackendKind::DockerHub,
+        None => BackendKind::Distribution,
+    }
+}
+
+/// returns the backend that handles the given registry host
+pub fn for_registry(registry: Option<&str>) -> Arc<dyn RegistryBackend> {
+    match select(registry, &OVERRIDES.lock().unwrap()) {
+        BackendKind::DockerHub => Arc::new(dockerhub::DockerHub),
+        BackendKind::Distribution => Arc::new(distribution::Distribution::new(match registry {
+            None | Some("docker.io") => "registry-1.docker.io",
+            Some(registry) => registry,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::BackendKind;
+
+    #[test]
+    fn test_select() {
+        let mut overrides = HashMap::new();
+        assert_eq!(super::select(None, &overrides), BackendKind::DockerHub);
+        assert_eq!(
+            super::select(Some("ghcr.io"), &overrides),
+            BackendKind::Distribution
+        );
+
+        overrides.insert(String::from("docker.io"), BackendKind::Distribution);
+        overrides.insert(String::from("hub.example.com"), BackendKind::DockerHub);
+        assert_eq!(super::select(None, &overrides), BackendKind::Distribution);
+        assert_eq!(
+            super::select(Some("hub.example.com"), &overrides),
+            BackendKind::DockerHub
+        );
+    }
+}
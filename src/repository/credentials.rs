@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use base64::Engine;
+use serde::Deserialize;
+
+/// the key under which docker stores the credentials of docker hub
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+
+lazy_static::lazy_static! {
+    /// directory given with --config, it takes precedence over DOCKER_CONFIG
+    static ref CONFIG_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+    /// credentials that were already looked up, the key is the registry
+    static ref CACHE: Mutex<HashMap<String, Option<Credentials>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub secret: String,
+}
+
+#[derive(Deserialize, Default)]
+struct AuthEntry {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HelperResponse {
+    #[serde(rename(deserialize = "Username"))]
+    username: String,
+    #[serde(rename(deserialize = "Secret"))]
+    secret: String,
+}
+
+/// the parts of the docker client config.json that are about authentication
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+    creds_store: Option<String>,
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+impl DockerConfig {
+    /// reads config.json from the given directory, a missing or broken file is treated as empty
+    fn load(dir: &Path) -> Self {
+        match std::fs::read_to_string(dir.join("config.json")) {
+            Err(_) => Self::default(),
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        }
+    }
+
+    /// returns the credentials of a registry
+    /// credential helpers are preferred over the credentials store and the credentials store
+    /// over the auths entries, as the docker client does it
+    fn credentials(&self, registry: &str) -> Option<Credentials> {
+        let registry = normalize_registry(registry);
+        let server = match registry.as_str() {
+            "docker.io" => DOCKER_HUB_SERVER,
+            registry => registry,
+        };
+
+        let helper = self
+            .cred_helpers
+            .iter()
+            .find(|(key, _)| normalize_registry(key) == registry)
+            .map(|(_, helper)| helper)
+            .or(self.creds_store.as_ref());
+        if let Some(credentials) =
+            helper.and_then(|h| run_helper(&format!("docker-credential-{}", h), server))
+        {
+            return Some(credentials);
+        }
+
+        let (_, entry) = self
+            .auths
+            .iter()
+            .find(|(key, _)| normalize_registry(key) == registry)?;
+        if let (Some(username), Some(secret)) = (&entry.username, &entry.password) {
+            return Some(Credentials {
+                username: username.clone(),
+                secret: secret.clone(),
+            });
+        }
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(entry.auth.as_ref()?)
+            .ok()?;
+        let (username, secret) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+        Some(Credentials {
+            username: username.to_string(),
+            secret: secret.to_string(),
+        })
+    }
+}
+
+/// sets the directory of the docker client config, None falls back to DOCKER_CONFIG or ~/.docker
+pub fn set_config_dir(dir: Option<PathBuf>) {
+    *CONFIG_DIR.lock().unwrap() = dir;
+    CACHE.lock().unwrap().clear();
+}
+
+fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = CONFIG_DIR.lock().unwrap().as_ref() {
+        return Some(dir.clone());
+    }
+    match std::env::var_os("DOCKER_CONFIG") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".docker")),
+    }
+}
+
+/// returns the credentials for a registry like ghcr.io or docker.io if there are any
+/// the config is read and credential helpers are run on a blocking thread
+pub async fn lookup(registry: &str) -> Option<Credentials> {
+    let registry = normalize_registry(registry);
+    if let Some(credentials) = CACHE.lock().unwrap().get(&registry) {
+        return credentials.clone();
+    }
+
+    let key = registry.clone();
+    let credentials = tokio::task::spawn_blocking(move || {
+        config_dir().and_then(|dir| DockerConfig::load(&dir).credentials(&key))
+    })
+    .await
+    .ok()
+    .flatten();
+    CACHE.lock().unwrap().insert(registry, credentials.clone());
+    credentials
+}
+
+/// removes scheme and path of a config key and maps the aliases of docker hub to docker.io
+fn normalize_registry(key: &str) -> String {
+    let key = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let host = key.split('/').next().unwrap_or_default();
+    match host {
+        "index.docker.io" | "registry-1.docker.io" | "hub.docker.com" => String::from("docker.io"),
+        host => host.to_string(),
+    }
+}
+
+/// asks a docker credential helper for the credentials of a server
+fn run_helper(program: &str, server: &str) -> Option<Credentials> {
+    let mut child = Command::new(program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(server.as_bytes()).ok()?;
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let response: HelperResponse = serde_json::from_slice(&output.stdout).ok()?;
+    Some(Credentials {
+        username: response.username,
+        secret: response.secret,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Credentials, DockerConfig};
+
+    fn credentials(username: &str, secret: &str) -> Option<Credentials> {
+        Some(Credentials {
+            username: username.into(),
+            secret: secret.into(),
+        })
+    }
+
+    #[test]
+    fn test_normalize_registry() {
+        assert_eq!(
+            super::normalize_registry("https://index.docker.io/v1/"),
+            "docker.io"
+        );
+        assert_eq!(
+            super::normalize_registry("registry-1.docker.io"),
+            "docker.io"
+        );
+        assert_eq!(super::normalize_registry("https://ghcr.io"), "ghcr.io");
+        assert_eq!(
+            super::normalize_registry("localhost:5000"),
+            "localhost:5000"
+        );
+    }
+
+    #[test]
+    fn test_auths() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{
+                "auths": {
+                    "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNz" },
+                    "registry.example.com": { "username": "bot", "password": "token" },
+                    "ghcr.io": {}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.credentials("docker.io"), credentials("user", "pass"));
+        assert_eq!(
+            config.credentials("registry.example.com"),
+            credentials("bot", "token")
+        );
+        assert_eq!(config.credentials("ghcr.io"), None);
+        assert_eq!(config.credentials("quay.io"), None);
+    }
+
+    #[test]
+    fn test_helper() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let helper = dir.join("docker-credential-test");
+        std::fs::write(
+            &helper,
+            "#!/bin/sh\nread server\necho \"{\\\"ServerURL\\\":\\\"$server\\\",\\\"Username\\\":\\\"$server\\\",\\\"Secret\\\":\\\"s3cret\\\"}\"\n",
+        )
+        .unwrap();
+        std::process::Command::new("chmod")
+            .arg("+x")
+            .arg(&helper)
+            .status()
+            .unwrap();
+
+        assert_eq!(
+            super::run_helper(helper.to_str().unwrap(), "ghcr.io"),
+            credentials("ghcr.io", "s3cret")
+        );
+        assert_eq!(
+            super::run_helper("docker-credential-missing", "ghcr.io"),
+            None
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, LINK};
+use reqwest::Url;
+use serde::Deserialize;
+
+use super::backend::{Page, RegistryBackend};
+use crate::error::Error;
+
+/// media types of manifests and indexes that can be resolved into details
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+application/vnd.docker.distribution.manifest.list.v2+json, \
+application/vnd.oci.image.manifest.v1+json, \
+application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Deserialize)]
+struct TagsList {
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Platform {
+    architecture: String,
+    os: String,
+    variant: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    digest: String,
+    #[serde(default)]
+    size: usize,
+    platform: Option<Platform>,
+}
+
+/// either an image manifest or a manifest list / image index
+#[derive(Deserialize)]
+struct Manifest {
+    /// entries of a manifest list or image index
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+    /// the config blob of an image manifest
+    config: Option<Descriptor>,
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    /// the digest the registry reported for the manifest
+    #[serde(skip)]
+    digest: Option<String>,
+}
+
+impl Manifest {
+    fn size(&self) -> usize {
+        self.layers.iter().map(|l| l.size).sum()
+    }
+}
+
+/// a registry that implements the OCI distribution spec, e.g. registry:2, ghcr.io or quay.io
+pub struct Distribution {
+    server: String,
+}
+
+impl Distribution {
+    pub fn new(server: &str) -> Self {
+        Self {
+            server: String::from(server),
+        }
+    }
+
+    /// fetches a manifest by tag or digest
+    async fn manifest(&self, repo: &str, reference: &str) -> Result<Manifest, Error> {
+        let request = format!(
+            "{}/v2/{}/manifests/{}",
+            base_url(&self.server),
+            repo,
+            reference
+        );
+        let response = super::auth::get_accepting(&request, Some(MANIFEST_TYPES))
+            .await?
+            .error_for_status()?;
+        let digest = response
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|h| h.to_str().ok())
+            .map(String::from);
+
+        let mut manifest = response.json::<Manifest>().await?;
+        manifest.digest = digest;
+        Ok(manifest)
+    }
+
+    /// reads the platform of a single image manifest from its config blob
+    async fn image_details(
+        &self,
+        repo: &str,
+        manifest: &Manifest,
+    ) -> Result<super::TagDetails, Error> {
+        let mut details = super::TagDetails {
+            arch: None,
+            variant: None,
+            os: None,
+            size: Some(manifest.size()),
+            digest: manifest.digest.clone(),
+        };
+        let Some(config) = &manifest.config else {
+            return Ok(details);
+        };
+
+        let request = format!(
+            "{}/v2/{}/blobs/{}",
+            base_url(&self.server),
+            repo,
+            config.digest
+        );
+        let platform = super::auth::get(&request)
+            .await?
+            .error_for_status()?
+            .json::<Platform>()
+            .await?;
+        details.arch = Some(platform.architecture);
+        details.variant = Some(platform.variant.unwrap_or_default());
+        details.os = Some(platform.os);
+        Ok(details)
+    }
+}
+
+#[async_trait]
+impl RegistryBackend for Distribution {
+    /// fetches tag information of a repository from the registry
+    async fn list_tags(&self, repo: &str) -> Result<Page, Error> {
+        let request = format!("{}/v2/{}/tags/list", base_url(&self.server), repo);
+        let page = self.next_page(&request).await?;
+        if page.tags.is_empty() {
+            return Err(Error::NoTagsFound);
+        }
+        Ok(page)
+    }
+
+    /// fetches tag information from a url
+    /// an empty page ends the list, even if the registry links another page
+    async fn next_page(&self, url: &str) -> Result<Page, Error> {
+        let response = super::auth::get(url).await?.error_for_status()?;
+        let next_page = next_link(url, response.headers());
+
+        //convert it to json
+        let list = response.json::<TagsList>().await?;
+        let tags = list.tags.unwrap_or_default();
+        if tags.is_empty() {
+            return Ok(Page {
+                tags: vec![],
+                next_page: None,
+            });
+        }
+
+        Ok(Page {
+            tags: tags
+                .into_iter()
+                .map(|name| super::Tag {
+                    name,
+                    details: vec![],
+                    last_updated: None,
+                    digest: None,
+                })
+                .collect(),
+            next_page,
+        })
+    }
+
+    /// fetches the manifest of a tag and resolves every platform of an index into details
+    async fn fetch_tag(&self, repo: &str, tag: &str) -> Result<super::Tag, Error> {
+        let manifest = self.manifest(repo, tag).await?;
+        let mut fetched = super::Tag {
+            name: String::from(tag),
+            details: vec![],
+            last_updated: None,
+            digest: manifest.digest.clone(),
+        };
+        if manifest.config.is_some() {
+            fetched.details = vec![self.image_details(repo, &manifest).await?];
+            return Ok(fetched);
+        }
+
+        let mut details = vec![];
+        for entry in &manifest.manifests {
+            //attestations are stored as manifests of an unknown platform
+            let Some(platform) = &entry.platform else {
+                continue;
+            };
+            if platform.os == "unknown" {
+                continue;
+            }
+
+            let image = self.manifest(repo, &entry.digest).await?;
+            details.push(super::TagDetails {
+                arch: Some(platform.architecture.clone()),
+                variant: Some(platform.variant.clone().unwrap_or_default()),
+                os: Some(platform.os.clone()),
+                size: Some(image.size()),
+                digest: Some(entry.digest.clone()),
+            });
+        }
+        fetched.details = details;
+        Ok(fetched)
+    }
+}
+
+/// returns the url under which the registry api is reachable
+/// local registries are expected to not use tls
+fn base_url(server: &str) -> String {
+    let host = server.split(':').next().unwrap_or_default();
+    match host {
+        "localhost" | "127.0.0.1" => format!("http://{}", server),
+        _ => format!("https://{}", server),
+    }
+}
+
+/// extracts the next page from a Link header in the form of `<url>; rel="next"`
+/// the link may be relative to the requested url
+fn next_link(url: &str, headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    let (target, params) = link.split_once(';')?;
+    if !params.contains("rel=\"next\"") {
+        return None;
+    }
+
+    let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+    let next = Url::parse(url).ok()?.join(target).ok()?;
+    Some(next.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::mock_registry::{MockRegistry, Response};
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(super::base_url("ghcr.io"), "https://ghcr.io");
+        assert_eq!(super::base_url("localhost:5000"), "http://localhost:5000");
+        assert_eq!(super::base_url("127.0.0.1"), "http://127.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn test_paginated_tags() {
+        let registry = MockRegistry::start(|request| match request.path.as_str() {
+            "/v2/library/nginx/tags/list" => {
+                Response::ok(r#"{"name":"library/nginx","tags":["1.24","1.25"]}"#).with_header(
+                    "Link",
+                    r#"</v2/library/nginx/tags/list?last=1.25&n=2>; rel="next""#,
+                )
+            }
+            "/v2/library/nginx/tags/list?last=1.25&n=2" => {
+                Response::ok(r#"{"name":"library/nginx","tags":["latest"]}"#)
+            }
+            _ => Response::status(404),
+        });
+
+        let repo = crate::repository::Repo::new(&format!("{}/library/nginx", registry.host()))
+            .await
+            .unwrap();
+        let names: Vec<&str> = repo.get_tags().iter().map(|t| t.get_name()).collect();
+        assert_eq!(names, vec!["1.24", "1.25"]);
+
+        let repo = repo.next_page().await.unwrap().unwrap();
+        let names: Vec<&str> = repo.get_tags().iter().map(|t| t.get_name()).collect();
+        assert_eq!(names, vec!["latest"]);
+        assert!(repo.next_page().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_empty_page() {
+        let registry = MockRegistry::start(|request| match request.path.as_str() {
+            "/v2/org/app/tags/list" => Response::ok(r#"{"tags":["1.0"]}"#)
+                .with_header("Link", r#"</v2/org/app/tags/list?last=1.0>; rel="next""#),
+            "/v2/org/app/tags/list?last=1.0" => Response::ok(r#"{"tags":[]}"#)
+                .with_header("Link", r#"</v2/org/app/tags/list?last=1.0>; rel="next""#),
+            "/v2/org/empty/tags/list" => Response::ok(r#"{"tags":null}"#),
+            _ => Response::status(404),
+        });
+
+        //an empty last page ends the list
+        let repo = crate::repository::Repo::new(&format!("{}/org/app", registry.host()))
+            .await
+            .unwrap();
+        assert_eq!(repo.fetch_all_tags().await.unwrap().len(), 1);
+        let last = repo.next_page().await.unwrap().unwrap();
+        assert!(last.get_tags().is_empty());
+        assert!(last.next_page().await.unwrap().is_none());
+
+        //a repository without any tag is an error
+        let empty = crate::repository::Repo::new(&format!("{}/org/empty", registry.host())).await;
+        assert!(matches!(empty, Err(crate::error::Error::NoTagsFound)));
+    }
+
+    #[tokio::test]
+    async fn test_index_details() {
+        let registry = MockRegistry::start(|request| match request.path.as_str() {
+            "/v2/org/app/tags/list" => Response::ok(r#"{"tags":["1.0"]}"#),
+            "/v2/org/app/manifests/1.0" => {
+                assert!(request
+                    .header("Accept")
+                    .unwrap()
+                    .contains("application/vnd.oci.image.index.v1+json"));
+                Response::ok(
+                    r#"{
+                        "mediaType": "application/vnd.oci.image.index.v1+json",
+                        "manifests": [
+                            { "digest": "sha256:amd", "size": 1, "platform": { "architecture": "amd64", "os": "linux" } },
+                            { "digest": "sha256:arm", "size": 1, "platform": { "architecture": "arm", "os": "linux", "variant": "v7" } },
+                            { "digest": "sha256:att", "size": 1, "platform": { "architecture": "unknown", "os": "unknown" } }
+                        ]
+                    }"#,
+                )
+                .with_header("Docker-Content-Digest", "sha256:index")
+            }
+            "/v2/org/app/manifests/sha256:amd" => Response::ok(
+                r#"{ "config": { "digest": "sha256:c1", "size": 1 }, "layers": [ { "digest": "sha256:l1", "size": 1048576 }, { "digest": "sha256:l2", "size": 2097152 } ] }"#,
+            ),
+            "/v2/org/app/manifests/sha256:arm" => Response::ok(
+                r#"{ "config": { "digest": "sha256:c2", "size": 1 }, "layers": [ { "digest": "sha256:l3", "size": 1024 } ] }"#,
+            ),
+            _ => Response::status(404),
+        });
+
+        let repo = format!("{}/org/app", registry.host());
+        let repo = crate::repository::Repo::new(&repo).await.unwrap();
+        let tag = repo.fetch_tag("1.0").await.unwrap();
+        let details = tag.get_details();
+        assert_eq!(tag.get_digest(), Some("sha256:index"));
+        assert_eq!(details.len(), 2);
+        assert_eq!(details[0].arch.as_deref(), Some("amd64"));
+        assert_eq!(details[0].digest.as_deref(), Some("sha256:amd"));
+        assert_eq!(details[0].size, Some(3145728));
+        assert_eq!(details[1].arch.as_deref(), Some("arm"));
+        assert_eq!(details[1].variant.as_deref(), Some("v7"));
+        assert_eq!(details[1].size, Some(1024));
+    }
+
+    #[tokio::test]
+    async fn test_manifest_details() {
+        let registry = MockRegistry::start(|request| {
+            match request.path.as_str() {
+            "/v2/org/app/tags/list" => Response::ok(r#"{"tags":["1.0"]}"#),
+            "/v2/org/app/manifests/1.0" => Response::ok(
+                r#"{ "config": { "digest": "sha256:c1", "size": 1 }, "layers": [ { "digest": "sha256:l1", "size": 10 }, { "digest": "sha256:l2", "size": 20 } ] }"#,
+            )
+            .with_header("Docker-Content-Digest", "sha256:image"),
+            "/v2/org/app/blobs/sha256:c1" => {
+                Response::ok(r#"{ "architecture": "arm64", "os": "linux", "rootfs": {} }"#)
+            }
+            _ => Response::status(404),
+        }
+        });
+
+        let repo = format!("{}/org/app", registry.host());
+        let repo = crate::repository::Repo::new(&repo).await.unwrap();
+        let tag = repo.fetch_tag("1.0").await.unwrap();
+        let details = tag.get_details();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].arch.as_deref(), Some("arm64"));
+        assert_eq!(details[0].os.as_deref(), Some("linux"));
+        assert_eq!(details[0].size, Some(30));
+        assert_eq!(details[0].digest.as_deref(), Some("sha256:image"));
+        assert_eq!(tag.get_digest(), Some("sha256:image"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_repo() {
+        let registry = MockRegistry::start(|_| Response::status(404));
+        let repo = format!("{}/library/nginx", registry.host());
+        assert!(crate::repository::Repo::new(&repo).await.is_err());
+    }
+}
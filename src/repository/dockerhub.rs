@@ -1,13 +1,31 @@
+use async_trait::async_trait;
 use serde::Deserialize;
 
+use super::backend::{Page, RegistryBackend};
 use crate::error::Error;
 
+/// the url under which the docker hub api is reachable
+pub const API_URL: &str = "https://hub.docker.com/";
+
 #[derive(Deserialize, Debug, Clone)]
 struct ImageDetails {
     architecture: String,
     os: String,
     variant: Option<String>,
     size: usize,
+    digest: Option<String>,
+}
+
+impl ImageDetails {
+    fn to_details(&self) -> super::TagDetails {
+        super::TagDetails {
+            arch: Some(self.architecture.clone()),
+            variant: Some(self.variant.clone().unwrap_or_default()),
+            os: Some(self.os.clone()),
+            size: Some(self.size),
+            digest: self.digest.clone(),
+        }
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -16,6 +34,7 @@ pub struct Images {
     #[serde(rename(deserialize = "name"))]
     tag_name: String,
     last_updated: String,
+    digest: Option<String>,
 }
 
 impl Images {
@@ -23,47 +42,53 @@ impl Images {
         super::Tag {
             name: images.tag_name.clone(),
             last_updated: Some(images.last_updated.clone()),
-            details: images
-                .images
-                .iter()
-                .map(|d| super::TagDetails {
-                    arch: Some(d.architecture.clone()),
-                    variant: Some(d.variant.clone().unwrap_or_default()),
-                    os: Some(d.os.clone()),
-                    size: Some(d.size),
-                })
-                .collect(),
+            digest: images.digest.clone(),
+            details: images.images.iter().map(ImageDetails::to_details).collect(),
         }
     }
 }
 
 #[derive(Deserialize)]
-pub struct DockerHub {
+struct TagsPage {
     #[serde(rename(deserialize = "next"))]
     next_page: Option<String>,
     results: Vec<Images>,
 }
 
-impl DockerHub {
+/// the api of hub.docker.com
+pub struct DockerHub;
+
+#[async_trait]
+impl RegistryBackend for DockerHub {
     /// fetches tag information with a repository name in the form of organization/repository or library/repository in the case of official images from docker
-    pub async fn create_repo(repo: &str) -> Result<super::Repo, Error> {
-        let request = format!("https://hub.docker.com/v2/repositories/{}/tags", repo);
-        Self::with_url(&request).await
+    async fn list_tags(&self, repo: &str) -> Result<Page, Error> {
+        let request = format!("{}v2/repositories/{}/tags?page_size=100", API_URL, repo);
+        self.next_page(&request).await
     }
 
     /// fetches tag information from a url
-    pub async fn with_url(url: &str) -> Result<super::Repo, Error> {
-        let response = reqwest::get(url).await?;
+    async fn next_page(&self, url: &str) -> Result<Page, Error> {
+        let response = super::auth::get(url).await?;
 
         //convert it to json
-        let tags = response.json::<Self>().await?;
+        let tags = response.json::<TagsPage>().await?;
         if tags.results.is_empty() {
             return Err(Error::NoTagsFound);
         }
 
-        Ok(super::Repo {
+        Ok(Page {
             tags: tags.results.iter().map(Images::from_tag).collect(),
             next_page: tags.next_page,
         })
     }
+
+    async fn fetch_tag(&self, repo: &str, tag: &str) -> Result<super::Tag, Error> {
+        let request = format!("{}v2/repositories/{}/tags/{}", API_URL, repo, tag);
+        let images = super::auth::get(&request)
+            .await?
+            .error_for_status()?
+            .json::<Images>()
+            .await?;
+        Ok(Images::from_tag(&images))
+    }
 }
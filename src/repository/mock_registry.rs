@@ -0,0 +1,110 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+/// a request as seen by the mock registry
+pub struct Request {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// returns the value of a header, the name is compared case insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// a response the mock registry sends back
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            body: String::from(body),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: String::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+}
+
+/// a minimal http server that stands in for a registry in tests
+pub struct MockRegistry {
+    port: u16,
+}
+
+impl MockRegistry {
+    /// starts the server in a background thread, every request is answered by the handler
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Response + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                //read request line and headers
+                let mut line = String::new();
+                if reader.read_line(&mut line).is_err() {
+                    continue;
+                }
+                let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                let mut headers = vec![];
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        headers.push((name.trim().to_string(), value.trim().to_string()));
+                    }
+                }
+
+                let response = handler(&Request { path, headers });
+                let mut head = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in &response.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str("\r\n");
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(response.body.as_bytes());
+            }
+        });
+
+        Self { port }
+    }
+
+    /// the host and port under which the server is reachable
+    pub fn host(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+}
@@ -1,4 +1,13 @@
+mod auth;
+mod backend;
+mod credentials;
+mod distribution;
 mod dockerhub;
+#[cfg(test)]
+pub(crate) mod mock_registry;
+mod tag_version;
+
+use std::sync::Arc;
 
 use chrono::DateTime;
 
@@ -6,12 +15,17 @@ use crate::common::display_duration_ext::DisplayDurationExt;
 use crate::error::Error;
 use crate::repo;
 
+pub use backend::{set_overrides, BackendKind, RegistryBackend};
+pub use credentials::set_config_dir;
+pub use tag_version::{sort_tags, suggest_upgrades, SortMode, Upgrade};
+
 #[derive(Clone, PartialEq)]
 pub struct TagDetails {
     pub arch: Option<String>,
     pub variant: Option<String>,
     pub os: Option<String>,
     pub size: Option<usize>,
+    pub digest: Option<String>,
 }
 
 #[derive(Clone)]
@@ -19,6 +33,7 @@ pub struct Tag {
     name: String,
     details: Vec<TagDetails>,
     last_updated: Option<String>,
+    digest: Option<String>,
 }
 
 impl Tag {
@@ -40,63 +55,91 @@ impl Tag {
         format!("{}{}", self.name, dif)
     }
 
+    /// the time of the last push in rfc3339 if the registry provides it
+    pub fn get_last_updated(&self) -> Option<&str> {
+        self.last_updated.as_deref()
+    }
+
     pub fn get_details(&self) -> &Vec<TagDetails> {
         &self.details
     }
+
+    /// the digest of the manifest or index the tag points to
+    pub fn get_digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
 }
 
 #[derive(Clone)]
 pub struct Repo {
+    name: String,
     tags: Vec<Tag>,
     next_page: Option<String>,
+    backend: Arc<dyn RegistryBackend>,
 }
 
 impl Repo {
     pub async fn new(repo: &str) -> Result<Self, Error> {
-        use crate::repo::Repo;
-        let (registry, repo) = match crate::repo::split_repo_without_tag(repo) {
-            Ok(Repo::WithServer(reg, org, pro)) => (Some(reg), format!("{}/{}", org, pro)),
-            Ok(Repo::WithOrga(org, pro)) => (None, format!("{}/{}", org, pro)),
-            Ok(Repo::Project(pro)) => (None, format!("library/{}", pro)),
+        let reference = match repo::Reference::parse(repo) {
+            Ok(reference) => reference,
             Err(e) => return Err(Error::Converting(format!("{}", e))),
         };
 
-        if registry.unwrap_or_default().is_empty() {
-            dockerhub::DockerHub::create_repo(&repo).await
-        } else {
-            Err(Error::Converting(
-                "This registry is not supported yet".into(),
-            ))
-        }
-    }
-
-    pub async fn with_url(url: &str) -> Result<Self, Error> {
-        //TODO fix for other registries
-        dockerhub::DockerHub::with_url(url).await
+        let backend = backend::for_registry(reference.domain.as_deref());
+        let page = backend.list_tags(&reference.path).await?;
+        Ok(Self {
+            name: reference.path,
+            tags: page.tags,
+            next_page: page.next_page,
+            backend,
+        })
     }
 
     pub fn get_tags(&self) -> &Vec<Tag> {
         &self.tags
     }
 
-    pub async fn next_page(&self) -> Option<Self> {
-        if let Some(url) = &self.next_page {
-            match Self::with_url(url).await {
-                Ok(tags) => return Some(tags),
-                Err(e) => println!("Encountered error: {e}"),
-            }
+    pub fn has_next_page(&self) -> bool {
+        self.next_page.is_some()
+    }
+
+    /// fetches the following page, None when this is the last page
+    pub async fn next_page(&self) -> Result<Option<Self>, Error> {
+        let Some(url) = &self.next_page else {
+            return Ok(None);
+        };
+        let page = self.backend.next_page(url).await?;
+        Ok(Some(Self {
+            name: self.name.clone(),
+            tags: page.tags,
+            next_page: page.next_page,
+            backend: self.backend.clone(),
+        }))
+    }
+
+    /// follows the remaining pages and returns the tags of all pages
+    pub async fn fetch_all_tags(&self) -> Result<Vec<Tag>, Error> {
+        let mut tags = self.tags.clone();
+        let mut next_page = self.next_page.clone();
+        while let Some(url) = next_page {
+            let page = self.backend.next_page(&url).await?;
+            tags.extend(page.tags);
+            next_page = page.next_page;
         }
-        None
+        Ok(tags)
+    }
+
+    /// fetches a tag of this repository with its digest and platform details
+    pub async fn fetch_tag(&self, tag: &str) -> Result<Tag, Error> {
+        self.backend.fetch_tag(&self.name, tag).await
     }
 }
 
 /// checks the repo name and may add a prefix for official images
+/// tag and digest are removed and docker hub aliases are normalized
 pub fn check_repo(name: &str) -> Result<String, Error> {
-    let repo = repo::split_tag_from_repo(name)?;
-
-    match repo::split_repo_without_tag(name) {
-        Ok(repo::Repo::Project(s)) => Ok(format!("library/{}", s)),
-        Ok(_) => Ok(repo.0.to_string()),
+    match repo::Reference::parse(name) {
+        Ok(reference) => Ok(reference.name()),
         Err(e) => Err(Error::Converting(format!("{}", e))),
     }
 }
@@ -111,5 +154,18 @@ mod tests {
             super::check_repo("rocketchat/rocket.chat").unwrap(),
             "rocketchat/rocket.chat"
         );
+        assert_eq!(super::check_repo("nginx:1.25").unwrap(), "library/nginx");
+        assert_eq!(
+            super::check_repo("nginx:1.25@sha256:0123abcd").unwrap(),
+            "library/nginx"
+        );
+        assert_eq!(
+            super::check_repo("docker.io/library/nginx").unwrap(),
+            "library/nginx"
+        );
+        assert_eq!(
+            super::check_repo("localhost:5000/team/app:1.0").unwrap(),
+            "localhost:5000/team/app"
+        );
     }
 }
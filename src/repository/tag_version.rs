@@ -0,0 +1,348 @@
+use std::cmp::Ordering;
+
+use chrono::DateTime;
+use regex::Regex;
+use serde::Serialize;
+
+use super::Tag;
+
+/// a tag that looks like a version, split into prefix, version numbers and suffix
+/// e.g. `v1.2.3` or `1.25.3-alpine3.19`
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagVersion {
+    pub prefix: String,
+    pub numbers: Vec<u64>,
+    pub suffix: String,
+}
+
+impl TagVersion {
+    /// returns None for tags that carry no version like `latest` or `sha-abc123`
+    pub fn parse(tag: &str) -> Option<Self> {
+        lazy_static::lazy_static! {
+            static ref REGEX: Regex =
+                Regex::new(r"^([vV]?)([0-9]+(?:\.[0-9]+)*)([-_+].*)?$").unwrap();
+        }
+        let caps = REGEX.captures(tag)?;
+        let numbers = caps
+            .get(2)?
+            .as_str()
+            .split('.')
+            .map(|n| n.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(Self {
+            prefix: caps.get(1).map(|c| c.as_str()).unwrap_or_default().into(),
+            numbers,
+            suffix: caps.get(3).map(|c| c.as_str()).unwrap_or_default().into(),
+        })
+    }
+
+    /// tags of the same variant share prefix, the suffix apart from its numbers
+    /// and the count of version numbers
+    /// e.g. `15.4-bookworm` and `16.1-bookworm` or `1.25.3-alpine3.18` and `1.25.4-alpine3.19`
+    pub fn same_variant(&self, other: &Self) -> bool {
+        self.prefix == other.prefix
+            && suffix_pattern(&self.suffix) == suffix_pattern(&other.suffix)
+            && self.numbers.len() == other.numbers.len()
+    }
+
+    /// the numbers inside the suffix, like 3 and 19 of `-alpine3.19`
+    fn suffix_numbers(&self) -> Vec<u64> {
+        self.suffix
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse().ok())
+            .collect()
+    }
+
+    /// returns the kind of upgrade when other is a newer version of the same variant
+    pub fn upgrade_to(&self, other: &Self) -> Option<Upgrade> {
+        if !self.same_variant(other) || other.numbers <= self.numbers {
+            return None;
+        }
+        match self
+            .numbers
+            .iter()
+            .zip(&other.numbers)
+            .position(|(a, b)| a != b)
+        {
+            Some(0) => Some(Upgrade::Major),
+            Some(1) => Some(Upgrade::Minor),
+            _ => Some(Upgrade::Patch),
+        }
+    }
+}
+
+/// replaces the numbers of a suffix, `-alpine3.19` becomes `-alpine#.#`
+fn suffix_pattern(suffix: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref NUMBER: Regex = Regex::new(r"[0-9]+").unwrap();
+    }
+    NUMBER.replace_all(suffix, "#").to_string()
+}
+
+/// the size of a step from one version to a newer one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Upgrade {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl std::fmt::Display for Upgrade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Upgrade::Patch => write!(f, "patch"),
+            Upgrade::Minor => write!(f, "minor"),
+            Upgrade::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// returns the newest tag per kind of upgrade that keeps the variant of the current tag
+/// of tags with the same version the one with the newest suffix like `alpine3.19` wins
+/// the result is ordered patch, minor, major
+pub fn suggest_upgrades(current: &str, tags: &[Tag]) -> Vec<(Upgrade, Tag)> {
+    let Some(current) = TagVersion::parse(current) else {
+        return vec![];
+    };
+
+    let mut newest: Vec<(Upgrade, TagVersion, &Tag)> = vec![];
+    for tag in tags {
+        let Some(version) = TagVersion::parse(&tag.name) else {
+            continue;
+        };
+        let Some(upgrade) = current.upgrade_to(&version) else {
+            continue;
+        };
+        match newest.iter_mut().find(|(u, _, _)| *u == upgrade) {
+            None => newest.push((upgrade, version, tag)),
+            Some(entry)
+                if (&entry.1.numbers, entry.1.suffix_numbers())
+                    < (&version.numbers, version.suffix_numbers()) =>
+            {
+                *entry = (upgrade, version, tag)
+            }
+            Some(_) => (),
+        }
+    }
+
+    newest.sort_by_key(|(upgrade, _, _)| *upgrade);
+    newest
+        .into_iter()
+        .map(|(upgrade, _, tag)| (upgrade, tag.clone()))
+        .collect()
+}
+
+/// the orders in which tags can be listed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    /// newest pushed first, the order docker hub returns
+    PushDate,
+    /// highest version first, tags without version at the end
+    Version,
+    /// alphabetical
+    Name,
+}
+
+impl SortMode {
+    /// returns the following sort mode
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::PushDate => SortMode::Version,
+            SortMode::Version => SortMode::Name,
+            SortMode::Name => SortMode::PushDate,
+        }
+    }
+}
+
+impl std::fmt::Display for SortMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortMode::PushDate => write!(f, "push date"),
+            SortMode::Version => write!(f, "version"),
+            SortMode::Name => write!(f, "name"),
+        }
+    }
+}
+
+/// sorts tags by the given mode, the sort is stable
+pub fn sort_tags(tags: &mut [Tag], mode: SortMode) {
+    match mode {
+        SortMode::PushDate => tags.sort_by(|a, b| {
+            let a = a
+                .last_updated
+                .as_deref()
+                .and_then(|d| DateTime::parse_from_rfc3339(d).ok());
+            let b = b
+                .last_updated
+                .as_deref()
+                .and_then(|d| DateTime::parse_from_rfc3339(d).ok());
+            match (a, b) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }),
+        SortMode::Version => {
+            tags.sort_by(
+                |a, b| match (TagVersion::parse(&a.name), TagVersion::parse(&b.name)) {
+                    (Some(va), Some(vb)) => vb
+                        .numbers
+                        .cmp(&va.numbers)
+                        .then_with(|| va.suffix.cmp(&vb.suffix)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => a.name.cmp(&b.name),
+                },
+            )
+        }
+        SortMode::Name => tags.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortMode, TagVersion, Upgrade};
+    use crate::repository::Tag;
+
+    fn version(prefix: &str, numbers: &[u64], suffix: &str) -> Option<TagVersion> {
+        Some(TagVersion {
+            prefix: prefix.into(),
+            numbers: numbers.to_vec(),
+            suffix: suffix.into(),
+        })
+    }
+
+    fn tag(name: &str, last_updated: Option<&str>) -> Tag {
+        Tag {
+            name: name.into(),
+            details: vec![],
+            last_updated: last_updated.map(String::from),
+            digest: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let input: Vec<(&str, Option<TagVersion>)> = vec![
+            ("1.25", version("", &[1, 25], "")),
+            ("v1.2.3", version("v", &[1, 2, 3], "")),
+            ("1.25.3-alpine3.19", version("", &[1, 25, 3], "-alpine3.19")),
+            ("15.4-bookworm", version("", &[15, 4], "-bookworm")),
+            ("20240101", version("", &[20240101], "")),
+            ("2.0.0_rc1", version("", &[2, 0, 0], "_rc1")),
+            ("latest", None),
+            ("mainline", None),
+            ("sha-abc123", None),
+        ];
+
+        for i in input {
+            assert_eq!(TagVersion::parse(i.0), i.1, "{}", i.0);
+        }
+    }
+
+    #[test]
+    fn test_same_variant() {
+        let a = TagVersion::parse("15.4-bookworm").unwrap();
+        assert!(a.same_variant(&TagVersion::parse("16.1-bookworm").unwrap()));
+        assert!(!a.same_variant(&TagVersion::parse("16.1-alpine").unwrap()));
+        assert!(!a.same_variant(&TagVersion::parse("16.1.2-bookworm").unwrap()));
+        assert!(!a.same_variant(&TagVersion::parse("16.1").unwrap()));
+
+        //the numbers of the suffix may change
+        let b = TagVersion::parse("1.25.3-alpine3.18").unwrap();
+        assert!(b.same_variant(&TagVersion::parse("1.25.4-alpine3.19").unwrap()));
+        assert!(!b.same_variant(&TagVersion::parse("1.25.4-alpine").unwrap()));
+        assert!(!b.same_variant(&TagVersion::parse("1.25.4-bookworm").unwrap()));
+    }
+
+    #[test]
+    fn test_upgrade_to() {
+        let input: Vec<(&str, &str, Option<Upgrade>)> = vec![
+            ("1.2.3", "1.2.4", Some(Upgrade::Patch)),
+            ("1.2.3", "1.3.0", Some(Upgrade::Minor)),
+            ("1.2.3", "2.0.0", Some(Upgrade::Major)),
+            ("15.4-bookworm", "15.5-bookworm", Some(Upgrade::Minor)),
+            ("v1.2.3", "v1.2.10", Some(Upgrade::Patch)),
+            ("1.2.3", "1.2.3", None),
+            ("1.2.3", "1.2.2", None),
+            ("1.2.3", "1.2.4-alpine", None),
+        ];
+
+        for i in input {
+            let current = TagVersion::parse(i.0).unwrap();
+            let other = TagVersion::parse(i.1).unwrap();
+            assert_eq!(current.upgrade_to(&other), i.2, "{} -> {}", i.0, i.1);
+        }
+    }
+
+    #[test]
+    fn test_suggest_upgrades() {
+        let tags = vec![
+            tag("latest", None),
+            tag("16.1-bookworm", None),
+            tag("17.0-bookworm", None),
+            tag("16.2-bookworm", None),
+            tag("15.5-bookworm", None),
+            tag("15.6-bookworm", None),
+            tag("15.6-alpine", None),
+            tag("15.4-bookworm", None),
+            tag("15.3-bookworm", None),
+        ];
+
+        let suggestions: Vec<(Upgrade, String)> = super::suggest_upgrades("15.4-bookworm", &tags)
+            .into_iter()
+            .map(|(u, t)| (u, t.name))
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                (Upgrade::Minor, String::from("15.6-bookworm")),
+                (Upgrade::Major, String::from("17.0-bookworm")),
+            ]
+        );
+        assert!(super::suggest_upgrades("latest", &tags).is_empty());
+
+        let tags = vec![
+            tag("1.25.4-alpine3.18", None),
+            tag("1.25.4-alpine3.19", None),
+            tag("1.25.4-alpine3.9", None),
+            tag("1.25.4-alpine", None),
+        ];
+        let suggestions = super::suggest_upgrades("1.25.3-alpine3.18", &tags);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].1.name, "1.25.4-alpine3.19");
+    }
+
+    #[test]
+    fn test_sort_tags() {
+        let mut tags = vec![
+            tag("latest", Some("2024-03-01T00:00:00Z")),
+            tag("1.9-alpine", Some("2023-01-01T00:00:00Z")),
+            tag("mainline", None),
+            tag("1.25", Some("2024-02-01T00:00:00Z")),
+            tag("1.10", Some("2024-01-01T00:00:00Z")),
+        ];
+        let names = |tags: &[Tag]| -> Vec<String> { tags.iter().map(|t| t.name.clone()).collect() };
+
+        super::sort_tags(&mut tags, SortMode::Version);
+        assert_eq!(
+            names(&tags),
+            vec!["1.25", "1.10", "1.9-alpine", "latest", "mainline"]
+        );
+
+        super::sort_tags(&mut tags, SortMode::Name);
+        assert_eq!(
+            names(&tags),
+            vec!["1.10", "1.25", "1.9-alpine", "latest", "mainline"]
+        );
+
+        super::sort_tags(&mut tags, SortMode::PushDate);
+        assert_eq!(
+            names(&tags),
+            vec!["latest", "1.25", "1.10", "1.9-alpine", "mainline"]
+        );
+    }
+}
@@ -1,7 +1,10 @@
 mod no_yaml_found;
 mod yaml_found;
+use std::path::Path;
+
 use anyhow::Result;
 
+use crate::widget::file_tree::FileTree;
 use crate::widget::service_switcher;
 use crate::Args;
 
@@ -13,9 +16,26 @@ pub enum UiEvent {
 pub fn create_ui(opt: &Args) -> Result<()> {
     let service_result = service_switcher::ServiceSwitcher::new(&opt.file);
     match service_result {
-        Some(switcher) => yaml_found::Ui::run(opt, switcher),
-        _ => no_yaml_found::Ui::run(opt),
+        Ok(switcher) => yaml_found::Ui::run(opt, switcher, None, None),
+        Err(service_switcher::Error::NoFile) => no_yaml_found::Ui::run(opt),
+        Err(e) => Err(e.into()),
     }?;
 
     Ok(())
 }
+
+/// opens every file below a directory that contains images and shows them as tree
+/// files that could not be opened are named in the info line
+pub fn create_scan_ui(opt: &Args, dir: &Path) -> Result<()> {
+    let (mut switcher, errors) = crate::cli::scan_files(dir)?;
+    let tree = FileTree::new(dir, &switcher.file_paths());
+    if let Some(file) = tree.selected() {
+        switcher.select_file(file);
+    }
+    let skipped: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    let info = match skipped.is_empty() {
+        true => None,
+        false => Some(format!("Skipped: {}", skipped.join("; "))),
+    };
+    yaml_found::Ui::run(opt, switcher, Some(tree), info)
+}
@@ -1,10 +1,10 @@
 use anyhow::Result;
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
 use ratatui::backend::TermionBackend;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::Terminal;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
 
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
@@ -54,6 +54,7 @@ pub enum DeferredEvent {
     Quit,
     NewRepo(String),
     LoadMoreTags,
+    LoadDetails,
 }
 
 impl Ui {
@@ -66,6 +67,21 @@ impl Ui {
         Ok(())
     }
 
+    /// fetches digest and platforms of the selected tag if the tag list did not contain them
+    async fn load_details(ui: &Arc<Mutex<Ui>>) {
+        let missing = ui.lock().unwrap().tags.missing_details();
+        if let Some((repo, tag)) = missing {
+            match repo.fetch_tag(&tag).await {
+                Err(e) => ui.lock().unwrap().info.set_info(&e),
+                Ok(tag) => {
+                    let mut ui = ui.lock().unwrap();
+                    ui.tags.update_tag(tag);
+                    ui.details = ui.tags.create_detail_widget();
+                }
+            }
+        }
+    }
+
     #[tokio::main]
     pub async fn work_requests(
         ui: &Arc<Mutex<Ui>>,
@@ -83,9 +99,14 @@ impl Ui {
                         ui.tags = TagList::with_status("fetching new tags...");
                         sender.send(UiEvent::RefreshOnNewData)?;
                     }
-                    let list = async_tag_list::TagList::with_repo_name(name).await;
-                    let mut ui = ui.lock().unwrap();
-                    ui.tags = list;
+                    let list = async_tag_list::TagList::with_repo_name(name, None).await;
+                    {
+                        let mut ui = ui.lock().unwrap();
+                        ui.tags = list;
+                        ui.details = ui.tags.create_detail_widget();
+                        sender.send(UiEvent::RefreshOnNewData)?;
+                    }
+                    Self::load_details(ui).await;
                 }
                 Ok(DeferredEvent::LoadMoreTags) if !fetching_tags.load(Ordering::Relaxed) => {
                     fetching_tags.store(true, Ordering::Relaxed);
@@ -105,14 +126,17 @@ impl Ui {
                             .build()
                             .unwrap()
                             .block_on(async {
-                                tags_copy.load_next_page().await;
+                                let loaded = tags_copy.load_next_page().await;
                                 let mut ui = ui_copy.lock().unwrap();
                                 //set position to the position of old TagList
                                 //it may have changed since tag fetching has been invoked
                                 tags_copy.set_cursor(ui.tags.get_cursor().clone());
                                 ui.tags = tags_copy;
                                 ui.details = ui.tags.create_detail_widget();
-                                ui.info.set_text("Fetching tags done");
+                                match loaded {
+                                    Err(e) => ui.info.set_info(&e),
+                                    Ok(_) => ui.info.set_text("Fetching tags done"),
+                                }
                                 sender_copy.send(UiEvent::RefreshOnNewData).unwrap();
                                 fetching_tags_copy.store(false, Ordering::Relaxed);
                             })
@@ -121,6 +145,7 @@ impl Ui {
                 Ok(DeferredEvent::LoadMoreTags) => {
                     //do nothing, as we are fetching tags
                 }
+                Ok(DeferredEvent::LoadDetails) => Self::load_details(ui).await,
                 Err(e) => {
                     let mut ui = ui.lock().unwrap();
                     ui.info.set_info(&e);
@@ -232,10 +257,16 @@ impl Ui {
                         ui_data.info.set_text("Editing Repository");
                         ui_data.repo.handle_input(Key::Backspace);
                     }
+                    //cycle the order of tags
+                    Key::Char('s') if ui_data.state == State::SelectTag => {
+                        let sort = ui_data.tags.cycle_sort();
+                        ui_data.info.set_text(&format!("Sorting tags by {}", sort));
+                    }
                     //moving up on selecting tags
                     Key::Up | Key::Char('k') if ui_data.state == State::SelectTag => {
                         ui_data.tags.previous();
                         ui_data.details = ui_data.tags.create_detail_widget();
+                        deferred_sender.send(DeferredEvent::LoadDetails)?;
                     }
                     //moving down on selecting tags
                     Key::Down | Key::Char('j') if ui_data.state == State::SelectTag => {
@@ -243,6 +274,7 @@ impl Ui {
                             deferred_sender.send(DeferredEvent::LoadMoreTags).unwrap();
                         }
                         ui_data.details = ui_data.tags.create_detail_widget();
+                        deferred_sender.send(DeferredEvent::LoadDetails)?;
                     }
                     //append character on editing repository
                     Key::Char(key) if ui_data.state == State::EditRepo => {
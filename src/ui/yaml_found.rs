@@ -1,18 +1,23 @@
 use anyhow::Result;
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
 use ratatui::backend::TermionBackend;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::Terminal;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
 
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use super::UiEvent;
 use crate::error::Error;
+use crate::repo::Reference;
 use crate::repository;
 use crate::widget::async_tag_list::{self, TagList};
+use crate::widget::diff_view::DiffView;
+use crate::widget::file_tree::FileTree;
 use crate::widget::{info, repo_entry, service_switcher};
 use crate::Args;
 
@@ -21,14 +26,21 @@ pub struct Ui {
     repo: repo_entry::RepoEntry,
     tags: TagList,
     services: service_switcher::ServiceSwitcher,
+    /// the scanned files when a directory is scanned
+    tree: Option<FileTree>,
     details: crate::widget::details::Details,
     info: info::Info,
+    /// the diff of unsaved changes while it is shown
+    diff: Option<DiffView>,
+    /// files with unsaved changes that were changed on disk, until it is decided what to do
+    conflict: Option<Vec<PathBuf>>,
 }
 
 #[derive(PartialEq, Clone)]
 pub enum State {
     EditRepo,
     SelectTag,
+    SelectFile,
     SelectService,
 }
 
@@ -37,6 +49,7 @@ impl std::fmt::Display for State {
         match self {
             State::EditRepo => write!(f, "Edit repository"),
             State::SelectTag => write!(f, "Select a tag"),
+            State::SelectFile => write!(f, "Select a file"),
             State::SelectService => write!(f, "Select a image"),
         }
     }
@@ -48,7 +61,8 @@ impl std::iter::Iterator for State {
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             State::EditRepo => *self = State::SelectTag,
-            State::SelectTag => *self = State::SelectService,
+            State::SelectTag => *self = State::SelectFile,
+            State::SelectFile => *self = State::SelectService,
             State::SelectService => *self = State::EditRepo,
         }
         Some(self.clone())
@@ -57,8 +71,10 @@ impl std::iter::Iterator for State {
 
 pub enum DeferredEvent {
     Quit,
-    NewRepo(String),
+    /// fetch the tags of a repository, the tag in use is highlighted if known
+    NewRepo(String, Option<String>),
     LoadMoreTags,
+    LoadDetails,
 }
 
 impl Ui {
@@ -71,6 +87,21 @@ impl Ui {
         Ok(())
     }
 
+    /// fetches digest and platforms of the selected tag if the tag list did not contain them
+    async fn load_details(ui: &Arc<Mutex<Ui>>) {
+        let missing = ui.lock().unwrap().tags.missing_details();
+        if let Some((repo, tag)) = missing {
+            match repo.fetch_tag(&tag).await {
+                Err(e) => ui.lock().unwrap().info.set_info(&e),
+                Ok(tag) => {
+                    let mut ui = ui.lock().unwrap();
+                    ui.tags.update_tag(tag);
+                    ui.details = ui.tags.create_detail_widget();
+                }
+            }
+        }
+    }
+
     #[tokio::main]
     pub async fn work_requests(
         ui: &Arc<Mutex<Ui>>,
@@ -82,15 +113,21 @@ impl Ui {
         loop {
             match events.recv() {
                 Ok(DeferredEvent::Quit) => break,
-                Ok(DeferredEvent::NewRepo(name)) => {
+                Ok(DeferredEvent::NewRepo(name, current)) => {
                     {
                         let mut ui = ui.lock().unwrap();
                         ui.tags = TagList::with_status("Fetching new tags...");
                         sender.send(UiEvent::RefreshOnNewData)?;
                     }
-                    let list = TagList::with_repo_name(name).await;
-                    let mut ui = ui.lock().unwrap();
-                    ui.tags = list;
+                    let list = TagList::with_repo_name(name, current).await;
+                    {
+                        let mut ui = ui.lock().unwrap();
+                        ui.tags = list;
+                        ui.details = ui.tags.create_detail_widget();
+                        sender.send(UiEvent::RefreshOnNewData)?;
+                    }
+                    //the preselected tag can be pinned right away
+                    Self::load_details(ui).await;
                 }
                 Ok(DeferredEvent::LoadMoreTags) if !fetching_tags.load(Ordering::Relaxed) => {
                     fetching_tags.store(true, Ordering::Relaxed);
@@ -110,14 +147,17 @@ impl Ui {
                             .build()
                             .unwrap()
                             .block_on(async {
-                                tags_copy.load_next_page().await;
+                                let loaded = tags_copy.load_next_page().await;
                                 let mut ui = ui_copy.lock().unwrap();
                                 //set position to the position of old TagList
                                 //it may have changed since tag fetching has been invoked
                                 tags_copy.set_cursor(ui.tags.get_cursor().clone());
                                 ui.tags = tags_copy;
                                 ui.details = ui.tags.create_detail_widget();
-                                ui.info.set_text("Fetching tags done");
+                                match loaded {
+                                    Err(e) => ui.info.set_info(&e),
+                                    Ok(_) => ui.info.set_text("Fetching tags done"),
+                                }
                                 sender_copy.send(UiEvent::RefreshOnNewData).unwrap();
                                 fetching_tags_copy.store(false, Ordering::Relaxed);
                             })
@@ -126,6 +166,7 @@ impl Ui {
                 Ok(DeferredEvent::LoadMoreTags) => {
                     //do nothing, as we are fetching tags
                 }
+                Ok(DeferredEvent::LoadDetails) => Self::load_details(ui).await,
                 Err(e) => {
                     let mut ui = ui.lock().unwrap();
                     ui.info.set_info(&e);
@@ -136,16 +177,93 @@ impl Ui {
         Ok(())
     }
 
-    pub fn run(opt: &Args, switcher: service_switcher::ServiceSwitcher) -> Result<()> {
+    /// checks the opened files for changes on disk, files without unsaved changes are reloaded
+    /// the files are checked without holding the lock, which is only taken when one of them changed
+    /// it stops when the ui quits and drops the sender of stop
+    fn watch_files(
+        ui: &Arc<Mutex<Ui>>,
+        sender: mpsc::Sender<UiEvent>,
+        stop: mpsc::Receiver<()>,
+    ) -> Result<(), Error> {
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop.recv_timeout(Duration::from_secs(1)) {
+            let watched = {
+                let ui = ui.lock().unwrap();
+                if ui.conflict.is_some() {
+                    continue;
+                }
+                ui.services.fingerprints()
+            };
+            if !watched.iter().any(|(path, f)| f.changed(path)) {
+                continue;
+            }
+
+            let mut ui = ui.lock().unwrap();
+            match ui.services.reload_unchanged() {
+                Err(e) => ui.info.set_info(&e),
+                Ok(reloaded) if reloaded.is_empty() => (),
+                Ok(reloaded) => {
+                    let text = format!("Reloaded {}", reloaded[0].display());
+                    ui.info.set_text(&text);
+                    sender.send(UiEvent::RefreshOnNewData)?;
+                }
+            }
+            let modified = ui.services.modified_files();
+            if !modified.is_empty() {
+                ui.show_conflict(modified);
+                sender.send(UiEvent::RefreshOnNewData)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// names the other images that changed with a variable of the .env file
+    fn show_also_changed(&mut self, others: &[service_switcher::Location]) {
+        if others.is_empty() {
+            return;
+        }
+        let names: Vec<String> = others
+            .iter()
+            .map(|l| {
+                self.services
+                    .service_name(*l)
+                    .unwrap_or_else(|| self.services.file_path(l.file).display().to_string())
+            })
+            .collect();
+        let text = format!("Changed the variable of {} as well", names.join(", "));
+        self.info.set_text(&text);
+    }
+
+    /// asks what to do with files that were changed here and on disk
+    fn show_conflict(&mut self, files: Vec<PathBuf>) {
+        let text = format!(
+            "{} was changed on disk: r Reload   o Overwrite   m Merge image lines",
+            files[0].display()
+        );
+        self.info.set_text(&text);
+        self.conflict = Some(files);
+    }
+
+    pub fn run(
+        opt: &Args,
+        switcher: service_switcher::ServiceSwitcher,
+        tree: Option<FileTree>,
+        info: Option<String>,
+    ) -> Result<()> {
         let repo_id = opt.repo.as_deref();
 
         let ui = Arc::new(Mutex::new(Ui {
-            state: State::SelectService,
+            state: match tree {
+                Some(_) => State::SelectFile,
+                None => State::SelectService,
+            },
             repo: repo_entry::RepoEntry::new(repo_id),
             tags: TagList::with_status("no tags"),
             services: switcher,
+            tree,
             details: crate::widget::details::Details::new(),
-            info: info::Info::new("Select image or edit Repository"),
+            info: info::Info::new(info.as_deref().unwrap_or("Select image or edit Repository")),
+            diff: None,
+            conflict: None,
         }));
 
         // spawn new thread that fetches information async
@@ -165,6 +283,17 @@ impl Ui {
         let backend = TermionBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
+        //setup watching the opened files, it stops when stop_watching is dropped
+        let (stop_watching, stop) = mpsc::channel();
+        let ui_clone = ui.clone();
+        let sender2 = sender.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = Self::watch_files(&ui_clone, sender2, stop) {
+                let mut ui = ui_clone.lock().unwrap();
+                ui.info.set_info(&e);
+            }
+        });
+
         //setup input thread
         let ui_clone = ui.clone();
         std::thread::spawn(move || {
@@ -178,19 +307,42 @@ impl Ui {
         'core: loop {
             //draw
             let mut ui_data = ui.lock().unwrap();
+            //the tree follows the image that is selected
+            if let Some(file) = ui_data.services.selected_file() {
+                if let Some(tree) = ui_data.tree.as_mut() {
+                    tree.select(file);
+                }
+            }
             terminal.draw(|rect| {
-                let chunks = Layout::default()
+                let mut constraints = vec![];
+                if ui_data.tree.is_some() {
+                    constraints.push(Constraint::Length(8));
+                }
+                constraints.extend([
+                    Constraint::Length(10),
+                    Constraint::Min(7),
+                    Constraint::Length(2),
+                ]);
+                let all_chunks = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints(
-                        [
-                            Constraint::Length(10),
-                            Constraint::Min(7),
-                            Constraint::Length(2),
-                        ]
-                        .as_ref(),
-                    )
+                    .constraints(constraints)
                     .split(rect.area());
+                let chunks = &all_chunks[all_chunks.len() - 3..];
+
+                //the diff covers the files and the tags while it is shown
+                if let Some(diff) = &mut ui_data.diff {
+                    let area = all_chunks[0].union(chunks[1]);
+                    let (diff, state) = diff.render();
+                    rect.render_stateful_widget(diff, area, state);
+                    rect.render_widget(ui_data.info.render(), chunks[2]);
+                    return;
+                }
 
+                let render_state = ui_data.state == State::SelectFile;
+                if let Some(tree) = ui_data.tree.as_mut() {
+                    let (tree, state) = tree.render(render_state);
+                    rect.render_stateful_widget(tree, all_chunks[0], state);
+                }
                 let render_state = ui_data.state == State::SelectService;
                 let (file, state) = ui_data.services.render(render_state);
                 rect.render_stateful_widget(file, chunks[0], state);
@@ -229,14 +381,71 @@ impl Ui {
                         deferred_sender.send(DeferredEvent::Quit)?;
                         break 'core; //quit program without saving
                     }
+                    //resolve files that were changed here and on disk
+                    Key::Char('r') if ui_data.conflict.is_some() => {
+                        match ui_data.services.reload() {
+                            Err(e) => ui_data.info.set_info(&e),
+                            Ok(_) => ui_data.info.set_text("Reloaded changed files"),
+                        }
+                        ui_data.conflict = None;
+                    }
+                    Key::Char('o') if ui_data.conflict.is_some() => {
+                        match ui_data.services.overwrite(opt.backup) {
+                            Err(e) => ui_data.info.set_info(&e),
+                            Ok(_) => ui_data.info.set_text("Overwrote changed files"),
+                        }
+                        ui_data.conflict = None;
+                    }
+                    Key::Char('m') if ui_data.conflict.is_some() => {
+                        match ui_data.services.merge() {
+                            //keep asking, only reloading or overwriting is possible
+                            Err(e) => {
+                                let text = format!("{}: r Reload   o Overwrite", e);
+                                ui_data.info.set_text(&text);
+                            }
+                            Ok(_) => {
+                                ui_data.info.set_text("Merged changed files, save with C-s");
+                                ui_data.conflict = None;
+                            }
+                        }
+                    }
+                    //ignore other input until the conflict is resolved
+                    _ if ui_data.conflict.is_some() => {}
+                    //show or hide the diff of unsaved changes
+                    Key::Ctrl('d') => match ui_data.diff {
+                        Some(_) => ui_data.diff = None,
+                        None => match ui_data.services.diff() {
+                            Err(e) => ui_data.info.set_info(&e),
+                            Ok(diff) => ui_data.diff = Some(DiffView::new(&diff)),
+                        },
+                    },
+                    Key::Esc if ui_data.diff.is_some() => ui_data.diff = None,
+                    Key::Up | Key::Char('k') if ui_data.diff.is_some() => {
+                        if let Some(diff) = &mut ui_data.diff {
+                            diff.previous();
+                        }
+                    }
+                    Key::Down | Key::Char('j') if ui_data.diff.is_some() => {
+                        if let Some(diff) = &mut ui_data.diff {
+                            diff.next();
+                        }
+                    }
+                    //ignore other input while the diff is shown
+                    _ if ui_data.diff.is_some() => {}
                     //cycle widgets
                     Key::Char('\t') => {
                         ui_data.state.next();
+                        if ui_data.state == State::SelectFile && ui_data.tree.is_none() {
+                            ui_data.state.next();
+                        }
                         let state = ui_data.state.clone();
                         ui_data.info.set_info(&state);
                     }
                     //save file
-                    Key::Ctrl('s') => match ui_data.services.save() {
+                    Key::Ctrl('s') => match ui_data.services.save(opt.backup) {
+                        Err(service_switcher::Error::Modified(files)) => {
+                            ui_data.show_conflict(files)
+                        }
                         Err(e) => {
                             ui_data.info.set_info(&format!("{}", e));
                             continue;
@@ -247,9 +456,25 @@ impl Ui {
                     Key::Ctrl('r') => {
                         ui_data.repo.confirm();
                         deferred_sender
-                            .send(DeferredEvent::NewRepo(ui_data.repo.get()))
+                            .send(DeferredEvent::NewRepo(ui_data.repo.get(), None))
                             .unwrap();
                     }
+                    //moving through the scanned files shows their images
+                    Key::Up | Key::Char('k') if ui_data.state == State::SelectFile => {
+                        if let Some(file) = ui_data.tree.as_mut().and_then(FileTree::previous) {
+                            ui_data.services.select_file(file);
+                        }
+                    }
+                    Key::Down | Key::Char('j') if ui_data.state == State::SelectFile => {
+                        if let Some(file) = ui_data.tree.as_mut().and_then(FileTree::next) {
+                            ui_data.services.select_file(file);
+                        }
+                    }
+                    //enter on a file continues with its images
+                    Key::Char('\n') if ui_data.state == State::SelectFile => {
+                        ui_data.state = State::SelectService;
+                        ui_data.info.set_info(&State::SelectService);
+                    }
                     //enter on selecting tags
                     Key::Char('\n') if ui_data.state == State::SelectTag => {
                         let mut repo = ui_data.repo.get();
@@ -263,13 +488,46 @@ impl Ui {
                         };
                         repo.push(':');
                         repo.push_str(&tag);
-                        ui_data.services.change_current_line(repo);
+                        match ui_data.services.change_current_line(repo) {
+                            Err(e) => ui_data.info.set_info(&e),
+                            Ok(others) => ui_data.show_also_changed(&others),
+                        }
+                    }
+                    //pin the selected tag with its digest
+                    Key::Char('p') if ui_data.state == State::SelectTag => {
+                        let mut repo = ui_data.repo.get();
+                        let tag = match ui_data.tags.get_selected() {
+                            Err(e) => {
+                                ui_data.info.set_info(&format!("{}", e));
+                                continue;
+                            }
+                            Ok(tag) => tag,
+                        };
+                        let digest = match ui_data.tags.get_selected_digest() {
+                            Ok(Some(digest)) => digest,
+                            Ok(None) => {
+                                ui_data.info.set_text("Digest of tag is not known yet");
+                                continue;
+                            }
+                            Err(e) => {
+                                ui_data.info.set_info(&format!("{}", e));
+                                continue;
+                            }
+                        };
+                        repo.push(':');
+                        repo.push_str(&tag);
+                        repo.push('@');
+                        repo.push_str(&digest);
+                        match ui_data.services.change_current_line(repo) {
+                            Err(e) => ui_data.info.set_info(&e),
+                            Ok(others) => ui_data.show_also_changed(&others),
+                        }
                     }
                     //enter on editing repository
                     Key::Char('\n') if ui_data.state == State::EditRepo => {
                         ui_data.repo.confirm();
                         deferred_sender
-                            .send(DeferredEvent::NewRepo(ui_data.repo.get()))
+                            .send(DeferredEvent::NewRepo(ui_data.repo.get(), None))
                             .unwrap();
                     }
                     //delete last char on repository
@@ -292,9 +550,16 @@ impl Ui {
                                     }
                                     Ok(s) => s,
                                 };
+                                let current = Reference::parse(&s).ok().and_then(|r| r.tag);
+                                if let Some(raw) = ui_data.services.extract_raw_repo() {
+                                    if raw != s {
+                                        let text = format!("{} resolves to {}", raw, s);
+                                        ui_data.info.set_text(&text);
+                                    }
+                                }
                                 ui_data.repo.set(repo.to_string());
                                 deferred_sender
-                                    .send(DeferredEvent::NewRepo(ui_data.repo.get()))
+                                    .send(DeferredEvent::NewRepo(ui_data.repo.get(), current))
                                     .unwrap();
                             }
                         }
@@ -314,17 +579,30 @@ impl Ui {
                                     }
                                     Ok(s) => s,
                                 };
+                                let current = Reference::parse(&s).ok().and_then(|r| r.tag);
+                                if let Some(raw) = ui_data.services.extract_raw_repo() {
+                                    if raw != s {
+                                        let text = format!("{} resolves to {}", raw, s);
+                                        ui_data.info.set_text(&text);
+                                    }
+                                }
                                 ui_data.repo.set(repo.to_string());
                                 deferred_sender
-                                    .send(DeferredEvent::NewRepo(ui_data.repo.get()))
+                                    .send(DeferredEvent::NewRepo(ui_data.repo.get(), current))
                                     .unwrap();
                             }
                         }
                     }
+                    //cycle the order of tags
+                    Key::Char('s') if ui_data.state == State::SelectTag => {
+                        let sort = ui_data.tags.cycle_sort();
+                        ui_data.info.set_text(&format!("Sorting tags by {}", sort));
+                    }
                     //moving up on selecting tags
                     Key::Up | Key::Char('k') if ui_data.state == State::SelectTag => {
                         ui_data.tags.previous();
                         ui_data.details = ui_data.tags.create_detail_widget();
+                        deferred_sender.send(DeferredEvent::LoadDetails)?;
                     }
                     //moving down on selecting tags
                     Key::Down | Key::Char('j') if ui_data.state == State::SelectTag => {
@@ -332,6 +610,7 @@ impl Ui {
                             deferred_sender.send(DeferredEvent::LoadMoreTags).unwrap();
                         }
                         ui_data.details = ui_data.tags.create_detail_widget();
+                        deferred_sender.send(DeferredEvent::LoadDetails)?;
                     }
                     //append character on editing repository
                     Key::Char(key) if ui_data.state == State::EditRepo => {
@@ -345,6 +624,7 @@ impl Ui {
             }
         }
 
+        drop(stop_watching);
         terminal.clear()?;
 
         Ok(())
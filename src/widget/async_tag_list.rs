@@ -3,7 +3,7 @@ use std::fmt;
 use ratatui::style::{Color, Style};
 use ratatui::widgets::{Block, Borders, List, ListState};
 
-use crate::repository;
+use crate::repository::{self, SortMode};
 
 pub enum Error {
     NoneSelected,
@@ -24,15 +24,29 @@ impl fmt::Display for Error {
 #[derive(Clone)]
 enum Line {
     Status(String),
+    Header(String),
     Image(repository::Tag),
+    Upgrade(repository::Upgrade, repository::Tag),
     NextPage(String),
 }
 
+impl Line {
+    /// returns the tag of a line that shows one
+    fn tag(&self) -> Option<&repository::Tag> {
+        match self {
+            Line::Image(t) | Line::Upgrade(_, t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for Line {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Line::Status(s) => write!(f, "{}", s),
+            Line::Header(s) => write!(f, "── {} ──", s),
             Line::Image(i) => write!(f, "{}", i.get_name_with_details()),
+            Line::Upgrade(u, i) => write!(f, "{}: {}", u, i.get_name_with_details()),
             Line::NextPage(s) => write!(f, "{}", s),
         }
     }
@@ -43,6 +57,11 @@ pub struct TagList {
     lines: Vec<Line>,
     state: ListState,
     tags: Option<repository::Repo>,
+    /// all fetched tags in the order they were fetched
+    loaded: Vec<repository::Tag>,
+    sort: SortMode,
+    /// the tag that is currently used in the opened file
+    current: Option<String>,
 }
 
 impl TagList {
@@ -52,40 +71,85 @@ impl TagList {
             lines: vec![Line::Status(String::from(status))],
             state: ListState::default(),
             tags: None,
+            loaded: vec![],
+            sort: SortMode::PushDate,
+            current: None,
         }
     }
 
     /// list the tags of the repository if the input is valid
-    pub async fn with_repo_name(repo: String) -> Self {
+    /// the current tag is highlighted and upgrades of it are suggested
+    pub async fn with_repo_name(repo: String, current: Option<String>) -> Self {
         match repository::Repo::new(&repo).await {
-            Ok(tags) => Self::with_tags(tags).await,
+            Ok(tags) => Self::with_tags(tags, current),
             Err(_) => Self::with_status("input repo was not found"),
         }
     }
 
     /// list the tags of the input
-    async fn with_tags(mut tags: repository::Repo) -> Self {
-        let mut lines: Vec<Line> = tags
-            .get_tags()
-            .iter()
-            .map(|r| Line::Image(r.clone()))
-            .collect();
+    fn with_tags(tags: repository::Repo, current: Option<String>) -> Self {
+        let mut list = Self {
+            lines: vec![],
+            state: ListState::default(),
+            loaded: tags.get_tags().clone(),
+            tags: Some(tags),
+            sort: SortMode::PushDate,
+            current,
+        };
+        list.rebuild_lines();
+        list
+    }
 
-        match tags.next_page().await {
-            None => (),
-            Some(new_tags) => {
-                lines.push(Line::NextPage(String::from("load more tags")));
-                tags = new_tags;
-            }
+    /// sorts the loaded tags into lines and keeps the selected tag selected
+    /// suggested upgrades of the current tag are put on top
+    fn rebuild_lines(&mut self) {
+        let selected = self.get_selected().ok();
+
+        self.lines = vec![];
+        //suggestions and order only know the tags of the loaded pages
+        let partial = self.tags.as_ref().is_some_and(|t| t.has_next_page());
+        let suggestions = match &self.current {
+            None => vec![],
+            Some(current) => repository::suggest_upgrades(current, &self.loaded),
         };
+        if !suggestions.is_empty() {
+            let header = match partial {
+                true => "suggested upgrades of the loaded tags",
+                false => "suggested upgrades",
+            };
+            self.lines.push(Line::Header(String::from(header)));
+            for (upgrade, tag) in suggestions {
+                self.lines.push(Line::Upgrade(upgrade, tag));
+            }
+            self.lines.push(Line::Header(String::from("all tags")));
+        }
 
-        Self {
-            lines,
-            state: ListState::default(),
-            tags: Some(tags),
+        let mut tags = self.loaded.clone();
+        repository::sort_tags(&mut tags, self.sort);
+        self.lines.extend(tags.into_iter().map(Line::Image));
+        if partial {
+            self.lines
+                .push(Line::NextPage(String::from("load more tags")));
+        }
+
+        if let Some(selected) = selected {
+            let position = self
+                .lines
+                .iter()
+                .position(|l| l.tag().is_some_and(|t| t.get_name() == selected));
+            self.state.select(position);
         }
     }
 
+    /// switches to the next sort mode and returns it
+    pub fn cycle_sort(&mut self) -> SortMode {
+        self.sort = self.sort.next();
+        if !self.loaded.is_empty() {
+            self.rebuild_lines();
+        }
+        self.sort
+    }
+
     pub fn set_cursor(&mut self, state: ListState) {
         self.state = state;
     }
@@ -94,7 +158,7 @@ impl TagList {
         &self.state
     }
 
-    pub fn render(&mut self, colored: bool) -> (List, &mut ListState) {
+    pub fn render(&mut self, colored: bool) -> (List<'_>, &mut ListState) {
         let border_style = if colored {
             Style::default().fg(Color::Green)
         } else {
@@ -105,8 +169,17 @@ impl TagList {
             .lines
             .iter()
             .map(|l| {
-                ratatui::widgets::ListItem::new(format!("{}", l))
-                    .style(Style::default().fg(Color::White).bg(Color::Black))
+                let is_current = match (l, &self.current) {
+                    (Line::Image(t), Some(current)) => t.get_name() == current,
+                    _ => false,
+                };
+                let style = match l {
+                    _ if is_current => Style::default().fg(Color::Yellow),
+                    Line::Header(_) => Style::default().fg(Color::Gray),
+                    Line::Upgrade(_, _) => Style::default().fg(Color::Green),
+                    _ => Style::default().fg(Color::White),
+                };
+                ratatui::widgets::ListItem::new(format!("{}", l)).style(style.bg(Color::Black))
             })
             .collect();
 
@@ -114,7 +187,12 @@ impl TagList {
         let items = List::new(items)
             .block(
                 Block::default()
-                    .title("Tags")
+                    .title(
+                        match self.tags.as_ref().is_some_and(|t| t.has_next_page()) {
+                            true => format!("Tags (by {}, more pages not loaded)", self.sort),
+                            false => format!("Tags (by {})", self.sort),
+                        },
+                    )
                     .borders(Borders::ALL)
                     .border_style(border_style),
             )
@@ -128,52 +206,75 @@ impl TagList {
     pub fn create_detail_widget(&self) -> crate::widget::details::Details {
         use crate::widget::details::Details;
 
-        match self.state.selected() {
-            None => Details::new(),
-            Some(i) => match &self.lines[i] {
-                Line::Image(t) => Details::with_list(t.get_details()),
-                _ => Details::new(),
-            },
+        match self.selected_tag() {
+            Ok(t) => Details::with_tag(t),
+            Err(_) => Details::new(),
         }
     }
 
-    pub fn get_selected(&mut self) -> Result<String, Error> {
+    fn selected_tag(&self) -> Result<&repository::Tag, Error> {
         match self.state.selected() {
             None => Err(Error::NoneSelected),
             Some(i) => match &self.lines[i] {
-                Line::Status(_) => Err(Error::SelectedStatus),
-                Line::Image(i) => Ok(i.get_name().to_string()),
+                Line::Status(_) | Line::Header(_) => Err(Error::SelectedStatus),
+                Line::Image(t) | Line::Upgrade(_, t) => Ok(t),
                 Line::NextPage(_) => Err(Error::NextPageSelected),
             },
         }
     }
 
+    pub fn get_selected(&mut self) -> Result<String, Error> {
+        self.selected_tag().map(|t| t.get_name().to_string())
+    }
+
+    /// returns the repository and the selected tag when the details of the tag are not fetched yet
+    pub fn missing_details(&self) -> Option<(repository::Repo, String)> {
+        let repo = self.tags.as_ref()?;
+        match self.selected_tag() {
+            Ok(t) if t.get_details().is_empty() => Some((repo.clone(), t.get_name().to_string())),
+            _ => None,
+        }
+    }
+
+    /// replaces a tag with a freshly fetched one that carries details and digest
+    pub fn update_tag(&mut self, tag: repository::Tag) {
+        for loaded in self.loaded.iter_mut() {
+            if loaded.get_name() == tag.get_name() {
+                *loaded = tag.clone();
+            }
+        }
+        for line in self.lines.iter_mut() {
+            match line {
+                Line::Image(t) | Line::Upgrade(_, t) if t.get_name() == tag.get_name() => {
+                    *t = tag.clone()
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// returns the digest of the selected tag if it is known
+    pub fn get_selected_digest(&self) -> Result<Option<String>, Error> {
+        self.selected_tag()
+            .map(|t| t.get_digest().map(String::from))
+    }
+
     /// load new tags from the next page
-    pub async fn load_next_page(&mut self) {
+    pub async fn load_next_page(&mut self) -> Result<(), crate::error::Error> {
         let Some(tags) = &self.tags else {
-            return;
+            return Ok(());
         };
-        let Some(new_tags) = tags.next_page().await else {
-            return;
+        let Some(new_tags) = tags.next_page().await? else {
+            return Ok(());
         };
 
-        //load new tags object
-        self.tags = Some(new_tags);
-
-        //remove "load next page"
-        let next_page = self.lines.pop();
-
         //add tags
-        if let Some(tags) = &self.tags {
-            for image in tags.get_tags().iter() {
-                self.lines.push(Line::Image(image.clone()));
-            }
-        }
+        self.loaded.extend(new_tags.get_tags().iter().cloned());
 
-        //readd next page item
-        if (self.tags.as_ref().unwrap().next_page().await).is_some() {
-            self.lines.push(next_page.unwrap());
-        }
+        //load new tags object
+        self.tags = Some(new_tags);
+        self.rebuild_lines();
+        Ok(())
     }
 
     /// select next tag
@@ -182,13 +283,13 @@ impl TagList {
         if let Some(Line::Status(_)) = self.lines.first() {
             return None;
         }
-        match self.state.selected() {
-            None if !self.lines.is_empty() => self.state.select(Some(0)),
-            None => (),
-            Some(i) if i == self.lines.len() - 2 => return Some(()),
-            // Some(i) if i == self.lines.len() - 2 => return self.load_next_page().await,
-            Some(i) => self.state.select(Some(i + 1)),
-        }
+        let next = match self.state.selected() {
+            None => 0,
+            Some(i) if matches!(self.lines.get(i + 1), Some(Line::NextPage(_))) => return Some(()),
+            Some(i) if i + 1 >= self.lines.len() => 0,
+            Some(i) => i + 1,
+        };
+        self.select_tag_line(next, true);
         None
     }
 
@@ -197,10 +298,29 @@ impl TagList {
         if let Some(Line::Status(_)) = self.lines.first() {
             return;
         }
-        match self.state.selected() {
-            None => self.state.select(Some(0)),
-            Some(0) => self.state.select(Some(self.lines.len() - 2)),
-            Some(i) => self.state.select(Some(i - 1)),
+        let last = match self.lines.last() {
+            Some(Line::NextPage(_)) => self.lines.len().saturating_sub(2),
+            _ => self.lines.len().saturating_sub(1),
+        };
+        let previous = match self.state.selected() {
+            None | Some(0) => last,
+            Some(i) => i - 1,
+        };
+        self.select_tag_line(previous, false);
+    }
+
+    /// selects the line at index or the nearest line with a tag in the given direction
+    fn select_tag_line(&mut self, index: usize, forward: bool) {
+        let mut index = index;
+        while let Some(Line::Header(_)) = self.lines.get(index) {
+            index = match (forward, index) {
+                (true, i) => i + 1,
+                (false, 0) => return,
+                (false, i) => i - 1,
+            };
+        }
+        if index < self.lines.len() {
+            self.state.select(Some(index));
         }
     }
 }
@@ -4,17 +4,22 @@ use ratatui::widgets::{Block, Borders, List};
 use crate::repository;
 
 pub struct Details {
+    digest: Option<String>,
     details: Vec<repository::TagDetails>,
 }
 
 impl Details {
     pub fn new() -> Self {
-        Self { details: vec![] }
+        Self {
+            digest: None,
+            details: vec![],
+        }
     }
 
-    pub fn with_list(details: &[crate::repository::TagDetails]) -> Self {
+    pub fn with_tag(tag: &repository::Tag) -> Self {
         let mut detail = Self {
-            details: details.to_owned(),
+            digest: tag.get_digest().map(String::from),
+            details: tag.get_details().to_owned(),
         };
 
         detail.details.sort_by(|a, b| a.arch.cmp(&b.arch));
@@ -23,10 +28,17 @@ impl Details {
     }
 
     pub fn get_details(&self) -> Vec<String> {
-        let mut lines = vec![format!("{:^10}|{:^6}|{:^6}", "ARCH", "OS", "SIZE")];
+        let mut lines = vec![];
+        if let Some(digest) = &self.digest {
+            lines.push(digest.clone());
+        }
+        lines.push(format!(
+            "{:^10}|{:^6}|{:^9}|{:^12}",
+            "ARCH", "OS", "SIZE", "DIGEST"
+        ));
         for d in &self.details {
             lines.push(format!(
-                "{:>10}|{:>6}|{:>6} MB",
+                "{:>10}|{:>6}|{:>6} MB|{:>12}",
                 format!(
                     "{}{}",
                     d.arch.clone().unwrap_or_default(),
@@ -34,12 +46,13 @@ impl Details {
                 ),
                 d.os.clone().unwrap_or_default(),
                 d.size.unwrap_or_default() / 1024 / 1024,
+                short_digest(d.digest.as_deref()),
             ));
         }
         lines
     }
 
-    pub fn render(&self) -> List {
+    pub fn render(&self) -> List<'_> {
         let items: Vec<ratatui::widgets::ListItem> = self
             .get_details()
             .iter()
@@ -59,3 +72,10 @@ impl Details {
             .style(Style::default().fg(Color::White).bg(Color::Black))
     }
 }
+
+/// shortens a digest to its first 12 hex characters like docker does
+fn short_digest(digest: Option<&str>) -> String {
+    let digest = digest.unwrap_or_default();
+    let hex = digest.split_once(':').map(|(_, h)| h).unwrap_or(digest);
+    hex.chars().take(12).collect()
+}
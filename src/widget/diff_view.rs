@@ -0,0 +1,65 @@
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Borders, List, ListState};
+
+/// shows a unified diff of the unsaved changes
+pub struct DiffView {
+    lines: Vec<String>,
+    state: ListState,
+}
+
+impl DiffView {
+    pub fn new(diff: &str) -> Self {
+        let lines = match diff.is_empty() {
+            true => vec![String::from("no unsaved changes")],
+            false => diff.lines().map(String::from).collect(),
+        };
+        Self {
+            lines,
+            state: ListState::default(),
+        }
+    }
+
+    pub fn render(&mut self) -> (List<'_>, &mut ListState) {
+        let items: Vec<ratatui::widgets::ListItem> = self
+            .lines
+            .iter()
+            .map(|l| {
+                let color = match l.chars().next() {
+                    Some('+') if !l.starts_with("+++") => Color::Green,
+                    Some('-') if !l.starts_with("---") => Color::Red,
+                    Some('@') => Color::Cyan,
+                    _ => Color::White,
+                };
+                ratatui::widgets::ListItem::new(l.clone())
+                    .style(Style::default().fg(color).bg(Color::Black))
+            })
+            .collect();
+
+        let items = List::new(items)
+            .block(
+                Block::default()
+                    .title("Unsaved changes (C-d or Esc to close)")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Green)),
+            )
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .highlight_style(Style::default().bg(Color::DarkGray));
+
+        (items, &mut self.state)
+    }
+
+    /// scroll one line down
+    pub fn next(&mut self) {
+        let next = match self.state.selected() {
+            None => 0,
+            Some(i) => (i + 1).min(self.lines.len() - 1),
+        };
+        self.state.select(Some(next));
+    }
+
+    /// scroll one line up
+    pub fn previous(&mut self) {
+        let previous = self.state.selected().unwrap_or(0).saturating_sub(1);
+        self.state.select(Some(previous));
+    }
+}
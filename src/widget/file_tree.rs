@@ -0,0 +1,203 @@
+use std::path::{Component, Path};
+
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Borders, List, ListState};
+
+/// a row of the tree, directories are only shown to group files
+#[derive(Debug, PartialEq)]
+enum Row {
+    Dir {
+        name: String,
+        depth: usize,
+    },
+    File {
+        name: String,
+        depth: usize,
+        file: usize,
+    },
+}
+
+/// shows the scanned files as tree below the scanned directory
+pub struct FileTree {
+    rows: Vec<Row>,
+    state: ListState,
+}
+
+impl FileTree {
+    /// builds the tree of files, a file is referred to by its index in the given paths
+    pub fn new(root: &Path, paths: &[&Path]) -> Self {
+        let components = |path: &Path| -> Vec<String> {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .components()
+                .filter(|c| !matches!(c, Component::CurDir))
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect()
+        };
+        let mut files: Vec<(Vec<String>, usize)> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (components(path), i))
+            .collect();
+        files.sort();
+
+        let mut rows = vec![];
+        let mut dirs: &[String] = &[];
+        for (components, file) in &files {
+            let (name, parents) = components.split_last().expect("a file has a name");
+            //directories that are shared with the previous file are already shown
+            let shared = dirs
+                .iter()
+                .zip(parents.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, dir) in parents.iter().enumerate().skip(shared) {
+                rows.push(Row::Dir {
+                    name: dir.clone(),
+                    depth,
+                });
+            }
+            rows.push(Row::File {
+                name: name.clone(),
+                depth: parents.len(),
+                file: *file,
+            });
+            dirs = parents;
+        }
+
+        let mut tree = Self {
+            rows,
+            state: ListState::default(),
+        };
+        tree.state.select(tree.position(|_| true));
+        tree
+    }
+
+    pub fn render(&mut self, colored: bool) -> (List<'_>, &mut ListState) {
+        let border_style = if colored {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+
+        let items: Vec<ratatui::widgets::ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Dir { name, depth } => {
+                    ratatui::widgets::ListItem::new(format!("{}{}/", "  ".repeat(*depth), name))
+                        .style(Style::default().fg(Color::Cyan).bg(Color::Black))
+                }
+                Row::File { name, depth, .. } => {
+                    ratatui::widgets::ListItem::new(format!("{}{}", "  ".repeat(*depth), name))
+                        .style(Style::default().fg(Color::White).bg(Color::Black))
+                }
+            })
+            .collect();
+
+        let items = List::new(items)
+            .block(
+                Block::default()
+                    .title("Scanned files")
+                    .borders(Borders::ALL)
+                    .border_style(border_style),
+            )
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .highlight_style(Style::default().bg(Color::Black))
+            .highlight_symbol(">>");
+
+        (items, &mut self.state)
+    }
+
+    /// returns the row of the first file that matches
+    fn position(&self, matches: impl Fn(usize) -> bool) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|row| matches!(row, Row::File { file, .. } if matches(*file)))
+    }
+
+    /// returns the index of the selected file
+    pub fn selected(&self) -> Option<usize> {
+        match self.rows.get(self.state.selected()?)? {
+            Row::File { file, .. } => Some(*file),
+            Row::Dir { .. } => None,
+        }
+    }
+
+    /// selects the row of a file
+    pub fn select(&mut self, file: usize) {
+        if let Some(row) = self.position(|f| f == file) {
+            self.state.select(Some(row));
+        }
+    }
+
+    /// selects the next file, directories are skipped
+    pub fn next(&mut self) -> Option<usize> {
+        let current = self.state.selected().unwrap_or(0);
+        let row =
+            (current + 1..self.rows.len()).find(|i| matches!(self.rows[*i], Row::File { .. }))?;
+        self.state.select(Some(row));
+        self.selected()
+    }
+
+    /// selects the previous file, directories are skipped
+    pub fn previous(&mut self) -> Option<usize> {
+        let current = self.state.selected().unwrap_or(0);
+        let row = (0..current)
+            .rev()
+            .find(|i| matches!(self.rows[*i], Row::File { .. }))?;
+        self.state.select(Some(row));
+        self.selected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{FileTree, Row};
+
+    #[test]
+    fn test_tree() {
+        let root = Path::new("repo");
+        let paths = [
+            Path::new("repo/services/web/compose.yaml"),
+            Path::new("repo/compose.yaml"),
+            Path::new("repo/services/web/Dockerfile"),
+            Path::new("repo/services/db/compose.yaml"),
+        ];
+        let mut tree = FileTree::new(root, &paths);
+
+        let dir = |name: &str, depth| Row::Dir {
+            name: name.to_string(),
+            depth,
+        };
+        let file = |name: &str, depth, file| Row::File {
+            name: name.to_string(),
+            depth,
+            file,
+        };
+        assert_eq!(
+            tree.rows,
+            vec![
+                file("compose.yaml", 0, 1),
+                dir("services", 0),
+                dir("db", 1),
+                file("compose.yaml", 2, 3),
+                dir("web", 1),
+                file("Dockerfile", 2, 2),
+                file("compose.yaml", 2, 0),
+            ]
+        );
+
+        assert_eq!(tree.selected(), Some(1));
+        assert_eq!(tree.next(), Some(3));
+        assert_eq!(tree.next(), Some(2));
+        assert_eq!(tree.next(), Some(0));
+        assert_eq!(tree.next(), None);
+        assert_eq!(tree.previous(), Some(2));
+        tree.select(1);
+        assert_eq!(tree.previous(), None);
+        assert_eq!(tree.selected(), Some(1));
+    }
+}
@@ -11,12 +11,12 @@ impl Info {
         Self {
             info: String::from(info),
             keys: String::from(
-                "Tab Cycle widgets   C-s Save   C-r Reload   C-q Quit   ↑ ↓ Select tags or image line   Return Select",
+                "Tab Cycle widgets   C-s Save   C-d Diff   C-r Reload   C-q Quit   ↑ ↓ Select tags or image line   Return Select   s Sort tags   p Pin digest",
             ),
         }
     }
 
-    pub fn render(&self) -> List {
+    pub fn render(&self) -> List<'_> {
         let items = vec![
             ListItem::new(self.info.clone()),
             ListItem::new(self.keys.clone()),
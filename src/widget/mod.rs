@@ -1,5 +1,7 @@
 pub mod async_tag_list;
 pub mod details;
+pub mod diff_view;
+pub mod file_tree;
 pub mod info;
 pub mod repo_entry;
 pub mod service_switcher;
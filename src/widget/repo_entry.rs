@@ -30,7 +30,7 @@ impl RepoEntry {
         self.old_text = entry;
     }
 
-    pub fn render(&self, colored: bool) -> Paragraph {
+    pub fn render(&self, colored: bool) -> Paragraph<'_> {
         let title = match self.changed {
             true => "Repository*",
             false => "Repository",
@@ -1,91 +1,231 @@
 use std::fmt;
-use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use ratatui::style::{Color, Style};
 use ratatui::widgets::{Block, Borders, List, ListState};
 
-use crate::repo;
+use crate::common::save::{Backup, Fingerprint};
+use crate::format::{self, opened_file::OpenedFile, scan};
 
 #[derive(Debug)]
 pub enum Error {
     NoneSelected,
-    Parsing(String),
+    File(format::Error),
+    /// none of the default compose files exists
+    NoFile,
+    Open(PathBuf, std::io::Error),
+    /// files that include each other, the first file is repeated at the end
+    Cycle(Vec<PathBuf>),
+    Write(PathBuf, std::io::Error),
+    /// files with unsaved changes that were changed on disk as well
+    Modified(Vec<PathBuf>),
+    /// lines of files that were changed here and on disk
+    Conflict(Vec<(PathBuf, Vec<usize>)>),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::NoneSelected => write!(f, "None selected"),
-            Error::Parsing(s) => write!(f, "Parsing error: {}", s),
+            Error::File(e) => write!(f, "{}", e),
+            Error::NoFile => write!(f, "No compose file found"),
+            Error::Open(path, e) => write!(f, "Could not open {}: {}", path.display(), e),
+            Error::Cycle(paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "Files include each other: {}", paths.join(" -> "))
+            }
+            Error::Write(path, e) => write!(f, "Could not save {}: {}", path.display(), e),
+            Error::Modified(paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "Changed on disk: {}", paths.join(", "))
+            }
+            Error::Conflict(files) => {
+                let files: Vec<String> = files
+                    .iter()
+                    .map(|(path, lines)| {
+                        let lines: Vec<String> =
+                            lines.iter().map(|l| (l + 1).to_string()).collect();
+                        format!("lines {} of {}", lines.join(", "), path.display())
+                    })
+                    .collect();
+                write!(f, "Changed on disk as well: {}", files.join("; "))
+            }
         }
     }
 }
 
+impl std::error::Error for Error {}
+
+/// the compose files docker compose looks for, the override files are stacked on top
+/// a Dockerfile in the same directory is opened as well
+const DEFAULT_FILES: [&str; 10] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+    "compose.override.yaml",
+    "compose.override.yml",
+    "docker-compose.override.yaml",
+    "docker-compose.override.yml",
+    "Dockerfile",
+    "Containerfile",
+];
+
+/// the position of a line in one of the opened files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub file: usize,
+    pub line: usize,
+}
+
+/// a row of the shown list
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    /// the name of a file, only shown when there is more than one file
+    Header(usize),
+    Line(Location),
+}
+
 pub struct ServiceSwitcher {
-    list: Vec<String>,
+    files: Vec<OpenedFile>,
     state: ListState,
-    changed: bool,
-    opened_file: PathBuf,
 }
 
 impl ServiceSwitcher {
-    pub fn new(file: &Option<PathBuf>) -> Option<Self> {
-        //gather possible filenames
-        let mut file_list = vec![
-            PathBuf::from("docker-compose.yml"),
-            PathBuf::from("docker-compose.yaml"),
-        ];
-        match &file {
-            None => (),
-            Some(file) => file_list.insert(0, file.clone()),
-        }
-
-        //try filenames
-        for file in file_list {
-            let list = match File::open(&file) {
-                Err(_) => continue,
-                Ok(file) => {
-                    let buf = BufReader::new(file);
-                    buf.lines()
-                        .map(|l| l.expect("Could not parse line"))
-                        .collect()
-                }
-            };
+    /// opens the given files or all compose files of the current directory
+    /// files that are referenced with `include:` or `extends:` are opened as well
+    pub fn new(files: &[PathBuf]) -> Result<Self, Error> {
+        let candidates: Vec<PathBuf> = match files.is_empty() {
+            true => DEFAULT_FILES
+                .iter()
+                .map(PathBuf::from)
+                .filter(|f| f.exists())
+                .collect(),
+            false => files.to_vec(),
+        };
+
+        //could not find docker-compose file
+        if candidates.is_empty() {
+            return Err(Error::NoFile);
+        }
 
-            return Some(Self {
-                list,
-                state: ListState::default(),
-                changed: false,
-                opened_file: file,
+        let mut opened = vec![];
+        for file in candidates {
+            Self::open_recursive(&file, &mut opened, &mut vec![])?;
+        }
+        Ok(Self {
+            files: opened,
+            state: ListState::default(),
+        })
+    }
+
+    /// opens every file below a directory that contains images
+    /// each file is opened on its own, so a file that can't be opened or refers to a missing file
+    /// does not stop the others, the errors of those files are returned with the switcher
+    pub fn scan(dir: &Path) -> Result<(Self, Vec<Error>), Error> {
+        let found = scan::find_files(dir);
+        let mut opened = vec![];
+        let mut errors = vec![];
+        for file in found {
+            if let Err(e) = Self::open_recursive(&file, &mut opened, &mut vec![]) {
+                errors.push(e);
+            }
+        }
+        if opened.is_empty() {
+            return Err(match errors.is_empty() {
+                true => Error::NoFile,
+                false => errors.remove(0),
             });
         }
+        let switcher = Self {
+            files: opened,
+            state: ListState::default(),
+        };
+        Ok((switcher, errors))
+    }
 
-        //could not find docker-compose file
-        None
+    /// opens a file and the files it refers to, chain holds the files that led to this file
+    fn open_recursive(
+        path: &Path,
+        opened: &mut Vec<OpenedFile>,
+        chain: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| Error::Open(path.to_path_buf(), e))?;
+        if let Some(start) = chain.iter().position(|p| *p == canonical) {
+            let mut cycle = chain[start..].to_vec();
+            cycle.push(canonical);
+            return Err(Error::Cycle(cycle));
+        }
+        let already_opened = opened
+            .iter()
+            .any(|f| f.path().canonicalize().ok().as_ref() == Some(&canonical));
+        if already_opened {
+            return Ok(());
+        }
+
+        //files of the same directory share their .env file
+        let sibling = opened.iter().find(|f| {
+            let other = f.path().canonicalize().ok();
+            other.as_deref().and_then(Path::parent) == canonical.parent()
+        });
+        let file = match sibling {
+            Some(sibling) => OpenedFile::open_with_env(path, Arc::clone(sibling.env())),
+            None => OpenedFile::open(path),
+        }
+        .map_err(|e| Error::Open(path.to_path_buf(), e))?;
+        let references = file.references().map_err(Error::File)?;
+        opened.push(file);
+
+        chain.push(canonical);
+        for reference in references {
+            Self::open_recursive(&reference, opened, chain)?;
+        }
+        chain.pop();
+        Ok(())
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = vec![];
+        for (file, opened) in self.files.iter().enumerate() {
+            if self.files.len() > 1 {
+                rows.push(Row::Header(file));
+            }
+            rows.extend((0..opened.lines().len()).map(|line| Row::Line(Location { file, line })));
+        }
+        rows
     }
 
-    pub fn render(&mut self, colored: bool) -> (List, &mut ListState) {
+    pub fn render(&mut self, colored: bool) -> (List<'_>, &mut ListState) {
         let border_style = if colored {
             Style::default().fg(Color::Green)
         } else {
             Style::default().fg(Color::Gray)
         };
 
-        let title = match &self.changed {
-            true => format!("File: *{}*", &self.opened_file.display()),
-            false => format!("File: {}", &self.opened_file.display()),
+        let name = |file: &OpenedFile| match file.changed() {
+            true => format!("*{}*", file.path().display()),
+            false => format!("{}", file.path().display()),
+        };
+        let title = match self.files.as_slice() {
+            [file] => format!("File: {}", name(file)),
+            _ => String::from("Files"),
         };
 
         let items: Vec<ratatui::widgets::ListItem> = self
-            .list
+            .rows()
             .iter()
-            .map(|l| {
-                ratatui::widgets::ListItem::new(l.clone())
-                    .style(Style::default().fg(Color::White).bg(Color::Black))
+            .map(|row| match row {
+                Row::Header(file) => {
+                    ratatui::widgets::ListItem::new(format!("── {} ──", name(&self.files[*file])))
+                        .style(Style::default().fg(Color::Cyan).bg(Color::Black))
+                }
+                Row::Line(l) => {
+                    ratatui::widgets::ListItem::new(self.files[l.file].lines()[l.line].clone())
+                        .style(Style::default().fg(Color::White).bg(Color::Black))
+                }
             })
             .collect();
 
@@ -104,24 +244,37 @@ impl ServiceSwitcher {
         (items, &mut self.state)
     }
 
+    /// returns the indices of the rows that contain an image
+    fn image_rows(&self) -> Vec<usize> {
+        let images = self.image_locations();
+        self.rows()
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| matches!(row, Row::Line(l) if images.contains(l)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     /// finds the next image tag in given file
     pub fn find_next_match(&mut self) -> bool {
-        let current_line: usize = self.state.selected().unwrap_or(0);
+        let current_row: usize = self.state.selected().unwrap_or(0);
+        let rows = self.rows().len();
+        let images = self.image_rows();
 
-        let mut i = (current_line + 1) % self.list.len();
+        let mut i = (current_row + 1) % rows;
         loop {
-            if i == current_line {
+            if i == current_row {
                 //looped through the list
                 break;
             }
 
             //check if line matches
-            if repo::match_yaml_image(&self.list[i]).is_ok() {
+            if images.contains(&i) {
                 self.state.select(Some(i));
                 return true;
             }
 
-            i = (i + 1) % self.list.len(); //iterate
+            i = (i + 1) % rows; //iterate
         }
 
         //nothing found
@@ -130,66 +283,519 @@ impl ServiceSwitcher {
 
     /// finds the previous image tag in given file
     pub fn find_previous_match(&mut self) -> bool {
-        let current_line: usize = self.state.selected().unwrap_or(0);
+        let current_row: usize = self.state.selected().unwrap_or(0);
+        let rows = self.rows().len();
+        let images = self.image_rows();
 
-        let mut i: usize = if current_line == 0 {
-            self.list.len() - 1
+        let mut i: usize = if current_row == 0 {
+            rows - 1
         } else {
-            current_line - 1
+            current_row - 1
         };
 
         loop {
-            if i == current_line {
+            if i == current_row {
                 //looped through the list
                 break;
             }
 
             //check if line matches
-            if repo::match_yaml_image(&self.list[i]).is_ok() {
+            if images.contains(&i) {
                 self.state.select(Some(i));
                 return true;
             }
 
             //iterate
-            i = if i == 0 { self.list.len() - 1 } else { i - 1 }
+            i = if i == 0 { rows - 1 } else { i - 1 }
         }
 
         //nothing found
         false
     }
 
+    /// returns the location of the selected row
+    fn selected(&self) -> Option<Location> {
+        match self.rows().get(self.state.selected()?) {
+            Some(Row::Line(location)) => Some(*location),
+            _ => None,
+        }
+    }
+
     /// return the repository from currently selected row
     pub fn extract_repo(&self) -> Result<String, Error> {
-        match self.state.selected() {
+        match self.selected() {
             None => Err(Error::NoneSelected),
-            Some(i) => match repo::match_yaml_image(&self.list[i]) {
-                Err(_) => Err(Error::Parsing(String::from("Nothing found"))),
-                Ok((_, repo)) => Ok(repo.to_string()),
-            },
+            Some(location) => self.get_image(location),
+        }
+    }
+
+    /// return the repository from currently selected row as it is written
+    pub fn extract_raw_repo(&self) -> Option<String> {
+        let location = self.selected()?;
+        self.files[location.file].get_raw_image(location.line)
+    }
+
+    /// returns the locations of all image lines, ordered by file
+    pub fn image_locations(&self) -> Vec<Location> {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(file, opened)| {
+                opened
+                    .image_lines()
+                    .into_iter()
+                    .map(move |line| Location { file, line })
+            })
+            .collect()
+    }
+
+    /// returns the image reference of a line with resolved variables
+    pub fn get_image(&self, location: Location) -> Result<String, Error> {
+        let file = self.files.get(location.file).ok_or(Error::NoneSelected)?;
+        file.get_image(location.line).map_err(Error::File)
+    }
+
+    /// selects the row of a location
+    pub fn select(&mut self, location: Location) {
+        if let Some(row) = self.rows().iter().position(|r| *r == Row::Line(location)) {
+            self.state.select(Some(row));
+        }
+    }
+
+    /// returns the name of the service the given line belongs to
+    pub fn service_name(&self, location: Location) -> Option<String> {
+        self.files.get(location.file)?.service_name(location.line)
+    }
+
+    /// the path of an opened file
+    pub fn file_path(&self, file: usize) -> &Path {
+        self.files[file].path()
+    }
+
+    /// the paths of all opened files
+    pub fn file_paths(&self) -> Vec<&Path> {
+        self.files.iter().map(OpenedFile::path).collect()
+    }
+
+    /// returns the file of the selected row
+    pub fn selected_file(&self) -> Option<usize> {
+        match self.rows().get(self.state.selected()?)? {
+            Row::Header(file) => Some(*file),
+            Row::Line(location) => Some(location.file),
+        }
+    }
+
+    /// selects the first image of a file or its header if it has none
+    pub fn select_file(&mut self, file: usize) {
+        let first = self.image_locations().into_iter().find(|l| l.file == file);
+        match first {
+            Some(location) => self.select(location),
+            None => {
+                let row = self.rows().iter().position(|r| *r == Row::Header(file));
+                self.state.select(row);
+            }
         }
     }
 
     /// replace currently selected line with repo and tag
-    pub fn change_current_line(&mut self, repo_with_tag: String) {
-        match self.state.selected() {
-            None => (),
-            Some(i) => match repo::match_yaml_image(&self.list[i]) {
-                Err(_) => return,
-                Ok((front, _)) => self.list[i] = format!("{}{}", front, repo_with_tag),
-            },
+    /// the change is made in the file that defines the image
+    /// returns the other images that resolve to something else now, because they use a changed variable
+    pub fn change_current_line(&mut self, repo_with_tag: String) -> Result<Vec<Location>, Error> {
+        let location = self.selected().ok_or(Error::NoneSelected)?;
+        let before: Vec<(Location, Option<String>)> = self
+            .image_locations()
+            .into_iter()
+            .map(|l| (l, self.get_image(l).ok()))
+            .collect();
+        self.files[location.file]
+            .change_image(location.line, &repo_with_tag)
+            .map_err(Error::File)?;
+        Ok(before
+            .into_iter()
+            .filter(|(l, image)| *l != location && self.get_image(*l).ok() != *image)
+            .map(|(l, _)| l)
+            .collect())
+    }
+
+    /// returns a unified diff between the files on disk and the shown lines
+    /// followed by the changes of the .env files
+    pub fn diff(&self) -> Result<String, std::io::Error> {
+        let mut result = String::new();
+        for file in &self.files {
+            result.push_str(&file.diff()?);
+        }
+        for (i, file) in self.files.iter().enumerate() {
+            let shown = self.files[..i]
+                .iter()
+                .any(|f| Arc::ptr_eq(f.env(), file.env()));
+            if !shown {
+                result.push_str(&file.env().lock().unwrap().diff());
+            }
         }
-        self.changed = true;
+        Ok(result)
     }
 
-    /// save the currently opened file
-    pub fn save(&mut self) -> Result<(), std::io::Error> {
-        let mut file = File::create(&self.opened_file)?;
-        for line in &self.list {
-            file.write_all(line.as_bytes())?;
-            file.write_all("\n".as_bytes())?;
+    /// save all changed files, unless one of them was changed on disk since it was read
+    pub fn save(&mut self, backup: Backup) -> Result<(), Error> {
+        let modified = self.modified_files();
+        if !modified.is_empty() {
+            return Err(Error::Modified(modified));
         }
+        self.overwrite(backup)
+    }
 
-        self.changed = false;
+    /// save all changed files, even if they were changed on disk
+    pub fn overwrite(&mut self, backup: Backup) -> Result<(), Error> {
+        for file in self.files.iter_mut() {
+            file.save(backup)
+                .map_err(|e| Error::Write(file.path().to_path_buf(), e))?;
+        }
         Ok(())
     }
+
+    /// the paths of the opened files and their .env files with their state on disk
+    /// to check them for changes without reading the files while the switcher is locked
+    pub fn fingerprints(&self) -> Vec<(PathBuf, Fingerprint)> {
+        self.files
+            .iter()
+            .flat_map(OpenedFile::fingerprints)
+            .collect()
+    }
+
+    /// returns the files with unsaved changes that were changed on disk
+    pub fn modified_files(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter(|f| f.changed() && f.modified_on_disk())
+            .map(|f| f.path().to_path_buf())
+            .collect()
+    }
+
+    /// reads the files again that were changed on disk and have no unsaved changes
+    /// and returns their paths
+    pub fn reload_unchanged(&mut self) -> Result<Vec<PathBuf>, Error> {
+        let mut reloaded = vec![];
+        for file in self.files.iter_mut() {
+            if !file.changed() && file.modified_on_disk() {
+                file.reload()
+                    .map_err(|e| Error::Open(file.path().to_path_buf(), e))?;
+                reloaded.push(file.path().to_path_buf());
+            }
+        }
+        self.keep_selection();
+        Ok(reloaded)
+    }
+
+    /// reads all files again that were changed on disk, unsaved changes of them are dropped
+    pub fn reload(&mut self) -> Result<(), Error> {
+        for file in self.files.iter_mut().filter(|f| f.modified_on_disk()) {
+            file.reload()
+                .map_err(|e| Error::Open(file.path().to_path_buf(), e))?;
+        }
+        self.keep_selection();
+        Ok(())
+    }
+
+    /// applies the unsaved changes to the files as they are on disk now
+    /// nothing is merged when one of the files has a conflict
+    pub fn merge(&mut self) -> Result<(), Error> {
+        let mut merges = vec![];
+        let mut conflicts = vec![];
+        for (i, file) in self.files.iter().enumerate() {
+            if !file.modified_on_disk() {
+                continue;
+            }
+            let path = file.path().to_path_buf();
+            let merge = file.merge().map_err(|e| Error::Open(path.clone(), e))?;
+            if !merge.conflicts.is_empty() {
+                conflicts.push((path, merge.conflicts.clone()));
+            }
+            merges.push((i, merge));
+        }
+        if !conflicts.is_empty() {
+            return Err(Error::Conflict(conflicts));
+        }
+
+        for (i, merge) in merges {
+            self.files[i].apply_merge(merge);
+        }
+        self.keep_selection();
+        Ok(())
+    }
+
+    /// drops the selection when the selected row does not exist anymore
+    fn keep_selection(&mut self) {
+        if self.state.selected() >= Some(self.rows().len()) {
+            self.state.select(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::widgets::ListState;
+
+    use super::{Error, Location, ServiceSwitcher};
+    use crate::common::save::Backup;
+    use crate::format::opened_file::OpenedFile;
+
+    #[test]
+    fn test_multiple_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("compose.yaml");
+        let overrides = dir.join("compose.override.yaml");
+        std::fs::write(&base, "services:\n  web:\n    image: nginx:1.25\n").unwrap();
+        std::fs::write(&overrides, "services:\n  db:\n    image: postgres:15.4\n").unwrap();
+
+        let mut switcher = ServiceSwitcher {
+            files: vec![
+                OpenedFile::open(&base).unwrap(),
+                OpenedFile::open(&overrides).unwrap(),
+            ],
+            state: ListState::default(),
+        };
+        let db = Location { file: 1, line: 2 };
+        assert_eq!(
+            switcher.image_locations(),
+            vec![Location { file: 0, line: 2 }, db]
+        );
+
+        //the header rows are skipped
+        assert!(switcher.find_next_match());
+        assert_eq!(switcher.extract_repo().unwrap(), "nginx:1.25");
+        assert!(switcher.find_next_match());
+        assert_eq!(switcher.extract_repo().unwrap(), "postgres:15.4");
+        assert_eq!(switcher.service_name(db), Some(String::from("db")));
+
+        switcher
+            .change_current_line(String::from("postgres:15.5"))
+            .unwrap();
+        switcher.save(Backup::None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&overrides).unwrap(),
+            "services:\n  db:\n    image: postgres:15.5\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&base).unwrap(),
+            "services:\n  web:\n    image: nginx:1.25\n"
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("db")).unwrap();
+        let base = dir.join("compose.yaml");
+        std::fs::write(
+            &base,
+            "include:\n  - db/compose.yaml\nservices:\n  web:\n    extends: { file: common.yaml, service: web }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("db/compose.yaml"),
+            "services:\n  db:\n    extends:\n      file: ../common.yaml\n      service: db\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common.yaml"),
+            "services:\n  web:\n    image: nginx:1.25\n  db:\n    image: postgres:15.4\n",
+        )
+        .unwrap();
+
+        //common.yaml is referenced twice but opened once
+        let switcher = ServiceSwitcher::new(std::slice::from_ref(&base)).unwrap();
+        assert_eq!(switcher.files.len(), 3);
+        assert_eq!(switcher.image_locations().len(), 2);
+
+        //variables in paths are resolved with the .env file
+        std::fs::write(dir.join(".env"), "STACK_DIR=db\n").unwrap();
+        std::fs::write(
+            &base,
+            "include:\n  - ${STACK_DIR}/compose.yaml\n  - path: ${MISSING:-db}/../common.yaml\n",
+        )
+        .unwrap();
+        let switcher = ServiceSwitcher::new(std::slice::from_ref(&base)).unwrap();
+        assert_eq!(switcher.files.len(), 3);
+        assert!(switcher.files[1].path().ends_with("db/compose.yaml"));
+
+        //missing files and cycles are errors
+        std::fs::write(dir.join("common.yaml"), "include:\n  - missing.yaml\n").unwrap();
+        assert!(matches!(
+            ServiceSwitcher::new(std::slice::from_ref(&base)),
+            Err(Error::Open(_, _))
+        ));
+        std::fs::write(dir.join("common.yaml"), "include:\n  - db/compose.yaml\n").unwrap();
+        match ServiceSwitcher::new(std::slice::from_ref(&base)) {
+            Err(Error::Cycle(cycle)) => assert_eq!(cycle.len(), 3),
+            _ => panic!("expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn test_scan_broken_include() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join(".gitlab-ci.yml"),
+            "include:\n  - '/templates/ci.yml'\nbuild:\n  image: rust:1.77\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("compose.yaml"),
+            "services:\n  web:\n    image: nginx:1.25\n",
+        )
+        .unwrap();
+
+        //the file with the missing include is kept, its include is reported
+        let (switcher, errors) = ServiceSwitcher::scan(dir).unwrap();
+        assert_eq!(switcher.files.len(), 2);
+        assert_eq!(switcher.image_locations().len(), 2);
+        match errors.as_slice() {
+            [Error::Open(path, _)] => assert_eq!(path, std::path::Path::new("/templates/ci.yml")),
+            _ => panic!("expected an error for the include"),
+        }
+    }
+
+    #[test]
+    fn test_shared_env_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("compose.yaml");
+        let overrides = dir.join("compose.override.yaml");
+        std::fs::write(&base, "services:\n  web:\n    image: nginx:${WEB_TAG}\n").unwrap();
+        std::fs::write(
+            &overrides,
+            "services:\n  db:\n    image: postgres:${DB_TAG}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join(".env"), "WEB_TAG=1.25\nDB_TAG=15.4\n").unwrap();
+
+        //both files change a variable of the same .env file
+        let mut switcher = ServiceSwitcher::new(&[base, overrides]).unwrap();
+        switcher.select(Location { file: 0, line: 2 });
+        switcher
+            .change_current_line(String::from("nginx:1.27"))
+            .unwrap();
+        switcher.select(Location { file: 1, line: 2 });
+        switcher
+            .change_current_line(String::from("postgres:16.1"))
+            .unwrap();
+        let diff = switcher.diff().unwrap();
+        switcher.save(Backup::None).unwrap();
+
+        assert_eq!(diff.matches("+WEB_TAG=1.27").count(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(".env")).unwrap(),
+            "WEB_TAG=1.27\nDB_TAG=16.1\n"
+        );
+        assert!(switcher.modified_files().is_empty());
+    }
+
+    #[test]
+    fn test_sibling_resolves_changed_variable() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("compose.yaml");
+        let overrides = dir.join("compose.override.yaml");
+        std::fs::write(&base, "services:\n  web:\n    image: nginx:${TAG}\n").unwrap();
+        std::fs::write(
+            &overrides,
+            "services:\n  proxy:\n    image: nginx:${TAG}-alpine\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join(".env"), "TAG=1.25\n").unwrap();
+
+        let mut switcher = ServiceSwitcher::new(&[base, overrides]).unwrap();
+        let proxy = Location { file: 1, line: 2 };
+        switcher.select(Location { file: 0, line: 2 });
+        let others = switcher
+            .change_current_line(String::from("nginx:1.27"))
+            .unwrap();
+
+        assert_eq!(others, vec![proxy]);
+        assert_eq!(switcher.get_image(proxy).unwrap(), "nginx:1.27-alpine");
+    }
+
+    #[test]
+    fn test_changed_on_disk() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("compose.yaml");
+        let content = "services:\n  web:\n    image: nginx:1.25\n  db:\n    image: postgres:15.4\n";
+        std::fs::write(&path, content).unwrap();
+
+        let mut switcher = ServiceSwitcher::new(std::slice::from_ref(&path)).unwrap();
+        switcher.select(Location { file: 0, line: 2 });
+        switcher
+            .change_current_line(String::from("nginx:1.27"))
+            .unwrap();
+        let watched = switcher.fingerprints();
+        assert_eq!(watched.len(), 2);
+        assert!(!watched.iter().any(|(path, f)| f.changed(path)));
+
+        //a new line above and a changed db image are merged
+        let theirs = format!("# edited\n{}", content.replace("15.4", "16.1"));
+        std::fs::write(&path, &theirs).unwrap();
+        assert!(watched.iter().any(|(path, f)| f.changed(path)));
+        assert_eq!(switcher.modified_files(), vec![path.clone()]);
+        assert!(matches!(
+            switcher.save(Backup::None),
+            Err(Error::Modified(_))
+        ));
+        switcher.merge().unwrap();
+        assert!(switcher.modified_files().is_empty());
+        switcher.save(Backup::None).unwrap();
+        let merged = std::fs::read_to_string(&path).unwrap();
+
+        //the same line changed on both sides is a conflict
+        switcher.select(Location { file: 0, line: 3 });
+        switcher
+            .change_current_line(String::from("nginx:1.28"))
+            .unwrap();
+        std::fs::write(&path, merged.replace("1.27", "1.27.1")).unwrap();
+        let conflict = switcher.merge();
+        switcher.reload().unwrap();
+        let reloaded = switcher.files[0].lines()[3].clone();
+        assert_eq!(merged, theirs.replace("1.25", "1.27"));
+        assert!(matches!(conflict, Err(Error::Conflict(files)) if files[0].1 == vec![3]));
+        assert_eq!(reloaded, "    image: nginx:1.27.1");
+    }
+
+    #[test]
+    fn test_merge_all_or_nothing() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("compose.yaml");
+        let overrides = dir.join("compose.override.yaml");
+        std::fs::write(&base, "services:\n  web:\n    image: nginx:1.25\n").unwrap();
+        std::fs::write(&overrides, "services:\n  db:\n    image: postgres:15.4\n").unwrap();
+
+        let mut switcher = ServiceSwitcher::new(&[base.clone(), overrides.clone()]).unwrap();
+        switcher.select(Location { file: 0, line: 2 });
+        switcher
+            .change_current_line(String::from("nginx:1.27"))
+            .unwrap();
+        switcher.select(Location { file: 1, line: 2 });
+        switcher
+            .change_current_line(String::from("postgres:16.1"))
+            .unwrap();
+
+        //the first file could be merged, the second one has a conflict
+        std::fs::write(
+            &base,
+            "# edited\nservices:\n  web:\n    image: nginx:1.25\n",
+        )
+        .unwrap();
+        std::fs::write(&overrides, "services:\n  db:\n    image: postgres:15.5\n").unwrap();
+        let conflict = switcher.merge();
+
+        match conflict {
+            Err(Error::Conflict(files)) => assert_eq!(files, vec![(overrides, vec![2])]),
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(switcher.files[0].lines()[0], "services:");
+        assert_eq!(switcher.modified_files().len(), 2);
+    }
 }